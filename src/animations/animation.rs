@@ -1,7 +1,8 @@
-use crate::emitters::emitter::Velocity;
+use crate::emitters::emitter::{AngularVelocity, Velocity};
 use bevy::math::Vec3;
 use bevy::render::color::Color;
 use std::fmt::Debug;
+use std::ops::Range;
 
 pub const FRAME_TIME: u32 = 16;
 
@@ -12,6 +13,53 @@ pub struct AnimationTime {
 
 pub trait Animate {
     fn animate(&self, data: &mut AnimationData, time: &AnimationTime);
+
+    /// Cycle ms in which the animation runs.
+    fn active_ms(&self) -> Range<u32>;
+
+    /// Parts of the AnimationData the animation writes, only these are blended.
+    fn channels(&self) -> Channels;
+
+    /// Inactive animations are skipped while blending.
+    fn is_active(&self, time: &AnimationTime) -> bool {
+        self.active_ms().contains(&time.cycle_ms)
+    }
+}
+
+/// Parts of the particle state an animation can write.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Channels {
+    pub color: bool,
+    pub scale: bool,
+    pub velocity: bool,
+    pub angular_velocity: bool,
+}
+
+impl Channels {
+    pub const COLOR: Self = Self {
+        color: true,
+        scale: false,
+        velocity: false,
+        angular_velocity: false,
+    };
+    pub const SCALE: Self = Self {
+        color: false,
+        scale: true,
+        velocity: false,
+        angular_velocity: false,
+    };
+    pub const VELOCITY: Self = Self {
+        color: false,
+        scale: false,
+        velocity: true,
+        angular_velocity: false,
+    };
+    pub const ANGULAR_VELOCITY: Self = Self {
+        color: false,
+        scale: false,
+        velocity: false,
+        angular_velocity: true,
+    };
 }
 
 impl Debug for dyn Animate {
@@ -20,8 +68,11 @@ impl Debug for dyn Animate {
     }
 }
 
-pub struct AnimationData<'a, 'b, 'c> {
+pub struct AnimationData<'a, 'b, 'c, 'd> {
     pub color: &'a mut Color,
     pub scale: &'b mut Vec3,
     pub velocity: &'c mut Velocity,
+    pub angular_velocity: &'d mut AngularVelocity,
+    /// Temperature of the particle in kelvin, if any.
+    pub temperature: Option<f32>,
 }
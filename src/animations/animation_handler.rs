@@ -1,16 +1,34 @@
-use bevy::prelude::Component;
+use bevy::math::{Vec3, Vec4};
+use bevy::prelude::{Color, Component};
 use rand::{thread_rng, Rng};
 
-use super::animation::{Animate, AnimationData, AnimationTime};
+use super::animation::{Animate, AnimationData, AnimationTime, Channels};
+use super::blend_mode::BlendMode;
+use crate::emitters::emitter::{AngularVelocity, Velocity};
 use std::fmt::Debug;
 
 #[derive(Component)]
 pub struct AnimationHandler {
     animation_offset_ms: u32,
-    animations: Vec<Box<dyn Animate + Sync + Send>>,
+    animations: Vec<AnimationLayer>,
     duration_ms: u32,
 }
 
+pub struct AnimationLayer {
+    pub animation: Box<dyn Animate + Sync + Send>,
+    pub blend_mode: BlendMode,
+    /// number between 0 and 1.
+    pub weight: f32,
+}
+
+/// Particle state before any animation of this frame is applied.
+struct AnimationBase {
+    color: Color,
+    scale: Vec3,
+    velocity: Velocity,
+    angular_velocity: AngularVelocity,
+}
+
 pub enum StartAnimationAt {
     Zero,
     Random,
@@ -18,7 +36,7 @@ pub enum StartAnimationAt {
 }
 
 pub struct AnimationOptions {
-    pub animations: Vec<Box<dyn Animate + Sync + Send>>,
+    pub animations: Vec<AnimationLayer>,
     pub duration_ms: u32,
     pub start_at: StartAnimationAt,
 }
@@ -53,12 +71,122 @@ impl AnimationHandler {
             total_ms: elapsed_ms,
         };
 
-        for animation in self.animations.iter() {
-            animation.animate(data, &time);
+        let base_color = *data.color;
+        let base_scale = *data.scale;
+        let base_velocity = *data.velocity;
+        let base_angular_velocity = *data.angular_velocity;
+
+        for layer in self.animations.iter() {
+            if !layer.animation.is_active(&time) {
+                continue;
+            }
+
+            // Override layers chain like plain animations, e.g. stacked velocity animations.
+            let (mut color, mut scale, mut velocity, mut angular_velocity) =
+                if layer.blend_mode == BlendMode::Override {
+                    (
+                        *data.color,
+                        *data.scale,
+                        *data.velocity,
+                        *data.angular_velocity,
+                    )
+                } else {
+                    (base_color, base_scale, base_velocity, base_angular_velocity)
+                };
+
+            let mut layer_data = AnimationData {
+                color: &mut color,
+                scale: &mut scale,
+                velocity: &mut velocity,
+                angular_velocity: &mut angular_velocity,
+                temperature: data.temperature,
+            };
+
+            layer.animation.animate(&mut layer_data, &time);
+
+            let base = AnimationBase {
+                color: base_color,
+                scale: base_scale,
+                velocity: base_velocity,
+                angular_velocity: base_angular_velocity,
+            };
+
+            layer.blend(data, &base, &layer_data);
         }
     }
 }
 
+impl AnimationLayer {
+    pub fn new(
+        animation: Box<dyn Animate + Sync + Send>,
+        blend_mode: BlendMode,
+        weight: f32,
+    ) -> Self {
+        Self {
+            animation,
+            blend_mode,
+            weight,
+        }
+    }
+
+    /// Only blends the channels the animation writes, so untouched channels keep the result
+    /// of the animations before it.
+    fn blend(&self, data: &mut AnimationData, base: &AnimationBase, output: &AnimationData) {
+        let Channels {
+            color,
+            scale,
+            velocity,
+            angular_velocity,
+        } = self.animation.channels();
+
+        if color {
+            let color = self.blend_mode.blend(
+                self.weight,
+                color_to_vec4(&base.color),
+                color_to_vec4(&*data.color),
+                color_to_vec4(&*output.color),
+            );
+            *data.color = Color::rgba(color.x, color.y, color.z, color.w);
+        }
+
+        if scale {
+            let scale = self.blend_mode.blend(
+                self.weight,
+                base.scale.extend(0.),
+                data.scale.extend(0.),
+                output.scale.extend(0.),
+            );
+            *data.scale = scale.truncate();
+        }
+
+        if velocity {
+            let velocity = self.blend_mode.blend(
+                self.weight,
+                base.velocity.to_vec3().extend(0.),
+                data.velocity.to_vec3().extend(0.),
+                output.velocity.to_vec3().extend(0.),
+            );
+            data.velocity.set_vec3(velocity.truncate());
+        }
+
+        if angular_velocity {
+            let angular_velocity = self.blend_mode.blend(
+                self.weight,
+                base.angular_velocity.to_vec3().extend(0.),
+                data.angular_velocity.to_vec3().extend(0.),
+                output.angular_velocity.to_vec3().extend(0.),
+            );
+            data.angular_velocity.set_vec3(angular_velocity.truncate());
+        }
+    }
+}
+
+impl From<Box<dyn Animate + Sync + Send>> for AnimationLayer {
+    fn from(animation: Box<dyn Animate + Sync + Send>) -> Self {
+        Self::new(animation, BlendMode::Override, 1.)
+    }
+}
+
 impl AnimationOptions {
     pub fn new(
         duration_ms: u32,
@@ -68,7 +196,130 @@ impl AnimationOptions {
         Self {
             duration_ms,
             start_at,
-            animations,
+            animations: animations.into_iter().map(AnimationLayer::from).collect(),
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        animation: Box<dyn Animate + Sync + Send>,
+        blend_mode: BlendMode,
+        weight: f32,
+    ) {
+        self.animations
+            .push(AnimationLayer::new(animation, blend_mode, weight));
+    }
+}
+
+fn color_to_vec4(color: &Color) -> Vec4 {
+    Vec4::new(color.r(), color.g(), color.b(), color.a())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animations::color_animation::DuoColorAnimation;
+    use crate::animations::spin_animation::SpinAnimation;
+    use std::ops::Range;
+
+    /// Adds to the velocity it is given.
+    struct Push(Vec3);
+
+    impl Animate for Push {
+        fn animate(&self, data: &mut AnimationData, _time: &AnimationTime) {
+            data.velocity.set_vec3(data.velocity.to_vec3() + self.0);
+        }
+
+        fn active_ms(&self) -> Range<u32> {
+            0..1000
         }
+
+        fn channels(&self) -> Channels {
+            Channels::VELOCITY
+        }
+    }
+
+    fn color_animation() -> Box<dyn Animate + Sync + Send> {
+        Box::new(DuoColorAnimation {
+            color_from: Color::rgba(1., 1., 1., 1.),
+            color_to: Color::rgba(0.5, 0.5, 0.5, 1.),
+            from_ms: 0,
+            until_ms: 1000,
+        })
+    }
+
+    fn animate(handler: &mut AnimationHandler) -> (Color, Vec3, Vec3, Vec3) {
+        let mut color = Color::rgba(1., 1., 1., 1.);
+        let mut scale = Vec3::splat(0.1);
+        let mut velocity = Velocity::new(1., 0., 0.);
+        let mut angular_velocity = AngularVelocity::zero();
+
+        handler.apply(
+            &mut AnimationData {
+                color: &mut color,
+                scale: &mut scale,
+                velocity: &mut velocity,
+                angular_velocity: &mut angular_velocity,
+                temperature: None,
+            },
+            500,
+        );
+
+        (color, scale, velocity.to_vec3(), angular_velocity.to_vec3())
+    }
+
+    #[test]
+    fn multiply_leaves_other_channels() {
+        let mut options = AnimationOptions::new(1000, StartAnimationAt::Zero, Vec::new());
+        options.add(color_animation(), BlendMode::Multiply, 1.);
+        let mut handler = AnimationHandler::new(options);
+
+        let (color, scale, _, _) = animate(&mut handler);
+
+        assert!((color.r() - 0.75).abs() < 0.001);
+        assert_eq!(scale, Vec3::splat(0.1));
+    }
+
+    #[test]
+    fn override_keeps_earlier_layers_of_other_channels() {
+        let spin = Box::new(SpinAnimation {
+            start_angular_velocity: Vec3::Y,
+            end_angular_velocity: Vec3::Y,
+            from_ms: 0,
+            until_ms: 1000,
+        });
+        let options =
+            AnimationOptions::new(1000, StartAnimationAt::Zero, vec![spin, color_animation()]);
+        let mut handler = AnimationHandler::new(options);
+
+        let (_, _, _, angular_velocity) = animate(&mut handler);
+
+        assert_eq!(angular_velocity, Vec3::Y);
+    }
+
+    #[test]
+    fn override_layers_chain() {
+        let options = AnimationOptions::new(
+            1000,
+            StartAnimationAt::Zero,
+            vec![Box::new(Push(Vec3::Y)), Box::new(Push(Vec3::Z))],
+        );
+        let mut handler = AnimationHandler::new(options);
+
+        let (_, _, velocity, _) = animate(&mut handler);
+
+        assert_eq!(velocity, Vec3::new(1., 1., 1.));
+    }
+
+    #[test]
+    fn additive_layers_add_their_change_to_the_base() {
+        let mut options = AnimationOptions::new(1000, StartAnimationAt::Zero, Vec::new());
+        options.add(Box::new(Push(Vec3::Y)), BlendMode::Additive, 1.);
+        options.add(Box::new(Push(Vec3::Y)), BlendMode::Additive, 0.5);
+        let mut handler = AnimationHandler::new(options);
+
+        let (_, _, velocity, _) = animate(&mut handler);
+
+        assert_eq!(velocity, Vec3::new(1., 1.5, 0.));
     }
 }
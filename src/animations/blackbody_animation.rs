@@ -0,0 +1,34 @@
+use super::animation::Animate;
+use super::animation::AnimationData;
+use super::animation::AnimationTime;
+use super::animation::Channels;
+use crate::emitters::temperature::blackbody_color;
+use std::ops::Range;
+
+/// Colors particles by their Temperature, e.g. flames cooling from white-yellow to dark red.
+pub struct BlackbodyAnimation {
+    pub from_ms: u32,
+    pub until_ms: u32,
+}
+
+impl Animate for BlackbodyAnimation {
+    fn animate(&self, data: &mut AnimationData, time: &AnimationTime) {
+        if !self.is_active(time) {
+            return;
+        }
+
+        if let Some(temperature) = data.temperature {
+            let alpha = data.color.a();
+            *data.color = blackbody_color(temperature);
+            data.color.set_a(alpha);
+        }
+    }
+
+    fn active_ms(&self) -> Range<u32> {
+        self.from_ms..self.until_ms
+    }
+
+    fn channels(&self) -> Channels {
+        Channels::COLOR
+    }
+}
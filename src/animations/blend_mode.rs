@@ -0,0 +1,28 @@
+use bevy::math::Vec4;
+
+/// How the output of an animation is combined with the animations before it.
+/// Override animations are evaluated on what the previous animations produced, so they chain,
+/// the other modes on the particle state from before this frame's animations. The result is then
+/// blended with `weight` (0..1) onto what the previous animations produced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlendMode {
+    /// Replaces the previous result, last overlapping animation wins.
+    Override,
+    /// Adds the change the animation made to the previous result.
+    Additive,
+    /// Multiplies the previous result with the animation output, e.g. a brightness flicker.
+    Multiply,
+    /// Takes the highest value per component.
+    Max,
+}
+
+impl BlendMode {
+    pub fn blend(&self, weight: f32, base: Vec4, current: Vec4, output: Vec4) -> Vec4 {
+        match self {
+            BlendMode::Override => current.lerp(output, weight),
+            BlendMode::Additive => current + (output - base) * weight,
+            BlendMode::Multiply => current * Vec4::ONE.lerp(output, weight),
+            BlendMode::Max => current.lerp(current.max(output), weight),
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use super::animation::Animate;
 use super::animation::AnimationData;
 use super::animation::AnimationTime;
+use super::animation::Channels;
 use bevy::render::color::Color;
+use std::ops::Range;
 
 #[derive(Clone, Debug)]
 pub struct DuoColorAnimation {
@@ -20,7 +22,7 @@ pub struct MonoColorAnimation {
 
 impl Animate for DuoColorAnimation {
     fn animate(&self, data: &mut AnimationData, time: &AnimationTime) {
-        if time.cycle_ms < self.from_ms || self.until_ms <= time.cycle_ms {
+        if !self.is_active(time) {
             return;
         }
 
@@ -39,6 +41,14 @@ impl Animate for DuoColorAnimation {
         data.color.set_b(b);
         data.color.set_a(a);
     }
+
+    fn active_ms(&self) -> Range<u32> {
+        self.from_ms..self.until_ms
+    }
+
+    fn channels(&self) -> Channels {
+        Channels::COLOR
+    }
 }
 
 //impl Animate for MonoColorAnimation {
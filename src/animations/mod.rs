@@ -1,6 +1,9 @@
 pub mod animation;
 pub mod animation_handler;
+pub mod blackbody_animation;
+pub mod blend_mode;
 pub mod color_animation;
 pub mod size_animation;
+pub mod spin_animation;
 pub mod stray_animation;
 pub mod weird_animation;
@@ -1,6 +1,8 @@
 use super::animation::Animate;
 use super::animation::AnimationData;
 use super::animation::AnimationTime;
+use super::animation::Channels;
+use std::ops::Range;
 
 pub struct SizeAnimation {
     pub start_scale: f32,
@@ -11,7 +13,7 @@ pub struct SizeAnimation {
 
 impl Animate for SizeAnimation {
     fn animate(&self, data: &mut AnimationData, time: &AnimationTime) {
-        if time.cycle_ms < self.from_ms || self.until_ms <= time.cycle_ms {
+        if !self.is_active(time) {
             return;
         }
 
@@ -25,4 +27,12 @@ impl Animate for SizeAnimation {
         //data.scale.y = scale;
         //data.scale.z = scale;
     }
+
+    fn active_ms(&self) -> Range<u32> {
+        self.from_ms..self.until_ms
+    }
+
+    fn channels(&self) -> Channels {
+        Channels::SCALE
+    }
 }
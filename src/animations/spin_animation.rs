@@ -0,0 +1,41 @@
+use super::animation::Animate;
+use super::animation::AnimationData;
+use super::animation::AnimationTime;
+use super::animation::Channels;
+use bevy::math::Vec3;
+use std::ops::Range;
+
+/// Changes the angular velocity (radians per second) of particles over time.
+pub struct SpinAnimation {
+    pub start_angular_velocity: Vec3,
+    pub end_angular_velocity: Vec3,
+    pub from_ms: u32,
+    pub until_ms: u32,
+}
+
+impl Animate for SpinAnimation {
+    fn animate(&self, data: &mut AnimationData, time: &AnimationTime) {
+        if !self.is_active(time) {
+            return;
+        }
+
+        let delta_current = time.cycle_ms - self.from_ms;
+        let delta_max = self.until_ms - self.from_ms;
+
+        // calculate percent from 0..1
+        let fraction = delta_current as f32 / delta_max as f32;
+        let angular_velocity = self
+            .start_angular_velocity
+            .lerp(self.end_angular_velocity, fraction);
+
+        data.angular_velocity.set_vec3(angular_velocity);
+    }
+
+    fn active_ms(&self) -> Range<u32> {
+        self.from_ms..self.until_ms
+    }
+
+    fn channels(&self) -> Channels {
+        Channels::ANGULAR_VELOCITY
+    }
+}
@@ -3,6 +3,8 @@ use crate::math::velocity::stray_velocity;
 use super::animation::Animate;
 use super::animation::AnimationData;
 use super::animation::AnimationTime;
+use super::animation::Channels;
+use std::ops::Range;
 
 pub struct StrayAnimation {
     from_ms: u32,
@@ -23,11 +25,19 @@ impl StrayAnimation {
 
 impl Animate for StrayAnimation {
     fn animate(&self, data: &mut AnimationData, time: &AnimationTime) {
-        if time.cycle_ms < self.from_ms || self.until_ms <= time.cycle_ms {
+        if !self.is_active(time) {
             return;
         }
 
         let velocity = &mut data.velocity;
         stray_velocity(velocity, self.stray_radians);
     }
+
+    fn active_ms(&self) -> Range<u32> {
+        self.from_ms..self.until_ms
+    }
+
+    fn channels(&self) -> Channels {
+        Channels::VELOCITY
+    }
 }
@@ -1,6 +1,8 @@
 use super::animation::Animate;
 use super::animation::AnimationData;
 use super::animation::AnimationTime;
+use super::animation::Channels;
+use std::ops::Range;
 
 pub struct WeirdAnimation {
     from_ms: u32,
@@ -20,7 +22,7 @@ impl WeirdAnimation {
 
 impl Animate for WeirdAnimation {
     fn animate(&self, data: &mut AnimationData, time: &AnimationTime) {
-        if time.cycle_ms < self.from_ms || self.until_ms <= time.cycle_ms {
+        if !self.is_active(time) {
             return;
         }
 
@@ -42,4 +44,12 @@ impl Animate for WeirdAnimation {
         velocity.vy += (vy - velocity.vy) * factor;
         velocity.vz += (vz - velocity.vz) * factor;
     }
+
+    fn active_ms(&self) -> Range<u32> {
+        self.from_ms..self.until_ms
+    }
+
+    fn channels(&self) -> Channels {
+        Channels::VELOCITY
+    }
 }
@@ -0,0 +1,582 @@
+use super::heightfield::Heightfield;
+use super::sleep::{Asleep, RestTimer};
+use super::substeps::{Substepped, Substeps};
+use super::triangle_mesh::TriangleMesh;
+use crate::emitters::emitter::{
+    velocity_to_emitter, velocity_to_world, AngularVelocity, Dead, Emitter, EmitterSystem,
+    Particle, ParticleAttributes, Velocity,
+};
+use bevy::prelude::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Steps of the conservative advancement of swept primitives.
+const MAX_SWEEP_ITERATIONS: u32 = 32;
+/// Distance at which a swept sphere touches a surface.
+const SWEEP_TOLERANCE: f32 = 0.001;
+/// Slower impacts don't send a ParticleCollided, so sliding and resting particles, that gain
+/// some speed into the surface from gravity every frame, don't send one every frame.
+pub const MIN_IMPACT_SPEED: f32 = 1.;
+
+/// Static shape that particles collide with, placed with the GlobalTransform of the entity.
+#[derive(Debug, Component, Clone)]
+pub struct Collider {
+    pub shape: ColliderShape,
+}
+
+#[derive(Debug, Clone)]
+pub enum ColliderShape {
+    /// Infinite plane through the origin with the local y axis as normal,
+    /// everything below it is solid, e.g. the ground.
+    Plane,
+    Sphere {
+        radius: f32,
+    },
+    Box {
+        half_extents: Vec3,
+    },
+    /// Along the local y axis.
+    Capsule {
+        radius: f32,
+        half_height: f32,
+    },
+    /// Only supports a uniform scale.
+    TriangleMesh(Arc<TriangleMesh>),
+    /// Only supports a uniform scale.
+    Heightfield(Arc<Heightfield>),
+}
+
+/// How particles of an emitter react when they hit a Collider.
+#[derive(Debug, Component, Clone, Copy)]
+pub enum ColliderResponse {
+    Bounce {
+        /// number between 0 and 1, (percentage of bounciness).
+        restitution: f32,
+        /// number between 0 and 1, fraction of the velocity along the surface that is lost.
+        friction: f32,
+    },
+    /// Moves along the surface without bouncing.
+    Slide {
+        friction: f32,
+    },
+    /// Stays attached to the collider, also when the collider moves.
+    Stick,
+    Die,
+}
+
+/// Particle that stuck to a collider.
+#[derive(Debug, Component)]
+pub struct Stuck {
+    pub collider: Entity,
+    /// Position of the particle in the space of the collider.
+    offset: Vec3,
+}
+
+/// Event for every particle that hits a collider faster than MIN_IMPACT_SPEED, e.g. to spawn
+/// splashes.
+#[derive(Debug, Clone)]
+pub struct ParticleCollided {
+    pub particle: Entity,
+    pub collider: Entity,
+    /// World space point on the surface of the collider.
+    pub point: Vec3,
+    /// World space surface normal, pointing away from the collider.
+    pub normal: Vec3,
+    /// Speed towards the surface at the moment of impact.
+    pub impact_speed: f32,
+}
+
+/// Deepest point of a particle inside a collider, in world space.
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub depth: f32,
+}
+
+/// Cost of the collision stage of the last frame, shown in the dev metrics.
+#[derive(Debug, Default)]
+pub struct CollisionMetrics {
+    pub substeps: u32,
+    pub duration: Duration,
+}
+
+/// Labels to order other systems around the collision stage.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub enum ColliderSystem {
+    /// Resolves the moves of the frame against the colliders, so particles have to be moved first.
+    Collide,
+}
+
+pub struct ColliderPlugin;
+
+impl Plugin for ColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ParticleCollided>()
+            .init_resource::<CollisionMetrics>()
+            .add_system(
+                collide_with_colliders_system
+                    .label(ColliderSystem::Collide)
+                    .after(EmitterSystem::Move),
+            )
+            .add_system(follow_stuck_particles_system);
+    }
+}
+
+impl Collider {
+    pub fn new(shape: ColliderShape) -> Self {
+        Self { shape }
+    }
+
+    /// Contact with a sphere at a world position, if they overlap.
+    pub fn contact(
+        &self,
+        transform: &GlobalTransform,
+        center: Vec3,
+        radius: f32,
+    ) -> Option<Contact> {
+        let local = transform.rotation.inverse() * (center - transform.translation);
+        let scale = transform.scale;
+
+        let (local_normal, distance) = match &self.shape {
+            ColliderShape::TriangleMesh(mesh) => {
+                let (normal, distance) = mesh.closest(local / scale.x, radius / scale.x)?;
+                (normal, distance * scale.x)
+            }
+            ColliderShape::Heightfield(heightfield) => {
+                let (normal, distance) = heightfield.contact(local / scale.x, radius / scale.x)?;
+                (normal, distance * scale.x)
+            }
+            _ => self.primitive_distance(local, scale)?,
+        };
+
+        if radius <= distance {
+            return None;
+        }
+
+        let normal = transform.rotation * local_normal;
+
+        Some(Contact {
+            point: center - normal * distance,
+            normal,
+            depth: radius - distance,
+        })
+    }
+
+    /// Contact of a sphere that moved from start to end through the surface, so fast particles
+    /// don't tunnel. Spheres that already overlap at the start or don't move are left to contact.
+    pub fn sweep(
+        &self,
+        transform: &GlobalTransform,
+        start: Vec3,
+        end: Vec3,
+        radius: f32,
+    ) -> Option<Contact> {
+        if start.distance(end) <= f32::EPSILON {
+            return None;
+        }
+
+        let inverse_rotation = transform.rotation.inverse();
+        let to_local = |point: Vec3| inverse_rotation * (point - transform.translation);
+        let scale = transform.scale;
+
+        match &self.shape {
+            ColliderShape::TriangleMesh(mesh) => {
+                // Meshes have no inside, the distance to the closest triangle is safe to move by.
+                Self::advance(transform, start, end, radius, |center, max_distance| {
+                    let (normal, distance) =
+                        mesh.closest(to_local(center) / scale.x, max_distance / scale.x)?;
+                    Some((normal, distance * scale.x))
+                })
+            }
+            ColliderShape::Heightfield(heightfield) => {
+                // The lowest point of the sphere is the first to go below the surface.
+                let lowest = Vec3::Y * radius;
+                let hit = heightfield.raycast(
+                    (to_local(start) - lowest) / scale.x,
+                    (to_local(end) - lowest) / scale.x,
+                )?;
+
+                let center = start.lerp(end, hit.fraction);
+                let normal = transform.rotation * hit.normal;
+
+                Some(Contact {
+                    point: center - normal * radius,
+                    normal,
+                    // Moves the particle back to where it touched the surface.
+                    depth: (center - end).dot(normal),
+                })
+            }
+            _ => Self::advance(transform, start, end, radius, |center, _| {
+                self.primitive_distance(to_local(center), scale)
+            }),
+        }
+    }
+
+    /// Conservative advancement, the sphere can safely move by its distance to the surface.
+    /// Distance_to gets a center and how far to look, and returns the local normal and distance.
+    fn advance(
+        transform: &GlobalTransform,
+        start: Vec3,
+        end: Vec3,
+        radius: f32,
+        distance_to: impl Fn(Vec3, f32) -> Option<(Vec3, f32)>,
+    ) -> Option<Contact> {
+        let path = end - start;
+        let length = path.length();
+        let mut travelled = 0.;
+
+        for _ in 0..MAX_SWEEP_ITERATIONS {
+            let center = start + path * (travelled / length);
+            let (local_normal, distance) = distance_to(center, radius + length - travelled)?;
+            let gap = distance - radius;
+
+            if gap <= SWEEP_TOLERANCE {
+                if travelled == 0. {
+                    return None;
+                }
+
+                let normal = transform.rotation * local_normal;
+
+                return Some(Contact {
+                    point: center - normal * distance,
+                    normal,
+                    depth: (center - end).dot(normal) + (radius - distance).max(0.),
+                });
+            }
+
+            travelled += gap;
+
+            if length <= travelled {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    /// Normal and signed distance to the surface of a primitive shape, in collider space.
+    fn primitive_distance(&self, local: Vec3, scale: Vec3) -> Option<(Vec3, f32)> {
+        let result = match &self.shape {
+            ColliderShape::Plane => (Vec3::Y, local.y),
+            ColliderShape::Sphere { radius } => {
+                let radius = radius * scale.max_element();
+                (direction_or(local, Vec3::Y), local.length() - radius)
+            }
+            ColliderShape::Box { half_extents } => box_distance(local, *half_extents * scale),
+            ColliderShape::Capsule {
+                radius,
+                half_height,
+            } => {
+                let radius = radius * scale.x.max(scale.z);
+                let half_height = half_height * scale.y;
+                let offset = local - Vec3::Y * local.y.clamp(-half_height, half_height);
+                (direction_or(offset, Vec3::X), offset.length() - radius)
+            }
+            ColliderShape::TriangleMesh(_) | ColliderShape::Heightfield(_) => return None,
+        };
+
+        Some(result)
+    }
+}
+
+/// Normal of the closest face and the signed distance to the surface of a box.
+fn box_distance(local: Vec3, half_extents: Vec3) -> (Vec3, f32) {
+    let closest = local.clamp(-half_extents, half_extents);
+    let outside = local - closest;
+
+    if outside != Vec3::ZERO {
+        return (outside.normalize(), outside.length());
+    }
+
+    // Inside the box, pushes out through the nearest face.
+    let depths = half_extents - local.abs();
+    let axis = if depths.x <= depths.y && depths.x <= depths.z {
+        Vec3::X
+    } else if depths.y <= depths.z {
+        Vec3::Y
+    } else {
+        Vec3::Z
+    };
+
+    let sign = if local.dot(axis) < 0. { -1. } else { 1. };
+    (axis * sign, -depths.dot(axis))
+}
+
+fn direction_or(vector: Vec3, fallback: Vec3) -> Vec3 {
+    if vector == Vec3::ZERO {
+        fallback
+    } else {
+        vector.normalize()
+    }
+}
+
+fn collide_with_colliders_system(
+    mut particles_query: Query<
+        (
+            Entity,
+            &Parent,
+            &mut Transform,
+            &mut Velocity,
+            &mut AngularVelocity,
+            &ParticleAttributes,
+            Option<&Substepped>,
+            Option<&mut RestTimer>,
+        ),
+        (
+            With<Particle>,
+            Without<Stuck>,
+            Without<Asleep>,
+            Without<Dead>,
+        ),
+    >,
+    emitter_query: Query<
+        (
+            &GlobalTransform,
+            Option<&ColliderResponse>,
+            Option<&Substeps>,
+        ),
+        With<Emitter>,
+    >,
+    collider_query: Query<(Entity, &GlobalTransform, &Collider)>,
+    mut events: EventWriter<ParticleCollided>,
+    mut metrics: ResMut<CollisionMetrics>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    let started_at = Instant::now();
+    let delta_seconds = time.delta_seconds();
+    let mut substep_count = 0;
+
+    for (
+        entity,
+        parent,
+        mut transform,
+        mut velocity,
+        mut angular_velocity,
+        attributes,
+        substepped,
+        rest_timer,
+    ) in particles_query.iter_mut()
+    {
+        let (emitter_transform, response, substeps) = match emitter_query.get(parent.0) {
+            Ok(emitter) => emitter,
+            Err(_) => continue,
+        };
+
+        if response.is_none() && substepped.is_none() {
+            continue;
+        }
+
+        // Particles are children of the emitter, collisions are resolved in world space.
+        let inverse_rotation = emitter_transform.rotation.inverse();
+        let mut motion = ParticleMotion {
+            entity,
+            position: emitter_transform.mul_vec3(transform.translation),
+            velocity: velocity_to_world(emitter_transform, velocity.to_vec3()),
+            // Particle meshes have a radius of 1, so the scale is the radius.
+            radius: (transform.scale * emitter_transform.scale).max_element(),
+            angular_velocity: angular_velocity.to_vec3(),
+            inverse_rotation,
+            touched: false,
+        };
+
+        let mut outcome = Outcome::Moved;
+
+        match (substepped, substeps) {
+            (Some(_), Some(substeps)) => {
+                // Substepped particles are moved here instead of by transform_particle_system.
+                motion.velocity *= 1. - attributes.friction_coefficient;
+
+                let count = substeps.count(motion.velocity.length() * delta_seconds, motion.radius);
+                let step_seconds = delta_seconds / count as f32;
+
+                for _ in 0..count {
+                    substep_count += 1;
+                    let start = motion.position;
+                    motion.position += motion.velocity * step_seconds;
+
+                    if let Some(response) = response {
+                        outcome = motion.resolve(start, *response, &collider_query, &mut events);
+
+                        if !matches!(outcome, Outcome::Moved) {
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => {
+                let response = match response {
+                    Some(response) => response,
+                    None => continue,
+                };
+
+                substep_count += 1;
+                let start = motion.position - motion.velocity * delta_seconds;
+                outcome = motion.resolve(start, *response, &collider_query, &mut events);
+            }
+        }
+
+        if let Some(mut rest_timer) = rest_timer {
+            if motion.touched {
+                rest_timer.touch_collider();
+            }
+        }
+
+        match outcome {
+            Outcome::Moved => {}
+            Outcome::Died => {
+                commands.entity(entity).insert(Dead);
+                continue;
+            }
+            Outcome::Stuck(stuck) => {
+                commands.entity(entity).insert(stuck);
+            }
+        }
+
+        transform.translation = inverse_rotation
+            * (motion.position - emitter_transform.translation)
+            / emitter_transform.scale;
+        velocity.set_vec3(velocity_to_emitter(emitter_transform, motion.velocity));
+        angular_velocity.set_vec3(motion.angular_velocity);
+    }
+
+    metrics.substeps = substep_count;
+    metrics.duration = started_at.elapsed();
+}
+
+/// World space state of a particle while it is resolved against the colliders.
+struct ParticleMotion {
+    entity: Entity,
+    position: Vec3,
+    velocity: Vec3,
+    radius: f32,
+    /// In emitter space.
+    angular_velocity: Vec3,
+    /// Of the emitter, to convert to emitter space.
+    inverse_rotation: Quat,
+    /// Touched a collider, also when too slow for an event.
+    touched: bool,
+}
+
+enum Outcome {
+    Moved,
+    Stuck(Stuck),
+    Died,
+}
+
+impl ParticleMotion {
+    /// Resolves the move from start to the current position against all colliders.
+    fn resolve(
+        &mut self,
+        start: Vec3,
+        response: ColliderResponse,
+        collider_query: &Query<(Entity, &GlobalTransform, &Collider)>,
+        events: &mut EventWriter<ParticleCollided>,
+    ) -> Outcome {
+        for (collider_entity, collider_transform, collider) in collider_query.iter() {
+            // Swept first, so particles that moved deep into a collider are pushed back out of
+            // the side they came in through and not the closest side.
+            let contact = match collider
+                .sweep(collider_transform, start, self.position, self.radius)
+                .or_else(|| collider.contact(collider_transform, self.position, self.radius))
+            {
+                Some(contact) => contact,
+                None => continue,
+            };
+
+            self.touched = true;
+            let impact_speed = -self.velocity.dot(contact.normal);
+
+            if MIN_IMPACT_SPEED < impact_speed {
+                events.send(ParticleCollided {
+                    particle: self.entity,
+                    collider: collider_entity,
+                    point: contact.point,
+                    normal: contact.normal,
+                    impact_speed,
+                });
+            }
+
+            match response {
+                ColliderResponse::Die => return Outcome::Died,
+                ColliderResponse::Stick => {
+                    self.position += contact.normal * contact.depth;
+                    self.velocity = Vec3::ZERO;
+                    self.angular_velocity = Vec3::ZERO;
+
+                    return Outcome::Stuck(Stuck {
+                        collider: collider_entity,
+                        offset: collider_transform.rotation.inverse()
+                            * (self.position - collider_transform.translation)
+                            / collider_transform.scale,
+                    });
+                }
+                ColliderResponse::Bounce {
+                    restitution,
+                    friction,
+                } => {
+                    let new_velocity = bounce(self.velocity, contact.normal, restitution, friction);
+                    let mut angular_velocity = AngularVelocity::from_vec3(self.angular_velocity);
+                    angular_velocity.add_tangential_impact(
+                        self.inverse_rotation * (-contact.normal * self.radius),
+                        self.inverse_rotation * (new_velocity - self.velocity),
+                    );
+
+                    self.angular_velocity = angular_velocity.to_vec3();
+                    self.position += contact.normal * contact.depth;
+                    self.velocity = new_velocity;
+                }
+                ColliderResponse::Slide { friction } => {
+                    self.position += contact.normal * contact.depth;
+                    self.velocity = bounce(self.velocity, contact.normal, 0., friction);
+                }
+            }
+        }
+
+        Outcome::Moved
+    }
+}
+
+/// Reflects the velocity towards the surface and slows down the velocity along it.
+fn bounce(velocity: Vec3, normal: Vec3, restitution: f32, friction: f32) -> Vec3 {
+    let normal_speed = velocity.dot(normal);
+
+    if 0. <= normal_speed {
+        return velocity;
+    }
+
+    let normal_velocity = normal * normal_speed;
+    let tangent_velocity = velocity - normal_velocity;
+
+    tangent_velocity * (1. - friction) - normal_velocity * restitution
+}
+
+fn follow_stuck_particles_system(
+    mut particles_query: Query<(Entity, &Parent, &Stuck, &mut Transform, &mut Velocity)>,
+    emitter_query: Query<&GlobalTransform, With<Emitter>>,
+    collider_query: Query<&GlobalTransform, With<Collider>>,
+    mut commands: Commands,
+) {
+    for (entity, parent, stuck, mut transform, mut velocity) in particles_query.iter_mut() {
+        let collider_transform = match collider_query.get(stuck.collider) {
+            Ok(collider_transform) => collider_transform,
+            Err(_) => {
+                // The collider is gone, so the particle falls off.
+                commands.entity(entity).remove::<Stuck>();
+                continue;
+            }
+        };
+
+        let emitter_transform = match emitter_query.get(parent.0) {
+            Ok(emitter_transform) => emitter_transform,
+            Err(_) => continue,
+        };
+
+        let position = collider_transform.mul_vec3(stuck.offset);
+        transform.translation = emitter_transform.rotation.inverse()
+            * (position - emitter_transform.translation)
+            / emitter_transform.scale;
+        velocity.set_vec3(Vec3::ZERO);
+    }
+}
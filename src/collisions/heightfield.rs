@@ -0,0 +1,265 @@
+use super::triangle_mesh::RayHit;
+use bevy::prelude::*;
+
+/// Grid of heights along the local x and z axis starting at the origin, e.g. terrain.
+/// Everything below the surface is solid.
+/// A single column or row makes a line of heights without any width.
+#[derive(Debug)]
+pub struct Heightfield {
+    columns: usize,
+    rows: usize,
+    /// Distance between the heights along x and z.
+    spacing: Vec2,
+    /// Row by row, each row has a height per column.
+    heights: Vec<f32>,
+}
+
+impl Heightfield {
+    pub fn new(columns: usize, rows: usize, spacing: Vec2, heights: Vec<f32>) -> Self {
+        assert!(
+            1 <= columns && 1 <= rows,
+            "Heightfield needs at least one height"
+        );
+        assert_eq!(
+            columns * rows,
+            heights.len(),
+            "Heightfield needs a height per point"
+        );
+
+        Self {
+            columns,
+            rows,
+            spacing,
+            heights,
+        }
+    }
+
+    pub fn from_fn(
+        columns: usize,
+        rows: usize,
+        spacing: Vec2,
+        height: impl Fn(usize, usize) -> f32,
+    ) -> Self {
+        let heights = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| height(column, row))
+            .collect();
+
+        Self::new(columns, rows, spacing, heights)
+    }
+
+    fn height(&self, column: usize, row: usize) -> f32 {
+        self.heights[row * self.columns + column]
+    }
+
+    /// Height and normal of the surface, None outside of the heightfield.
+    /// Every cell is split into two triangles along its diagonal.
+    pub fn surface_at(&self, x: f32, z: f32) -> Option<(f32, Vec3)> {
+        let grid_x = x / self.spacing.x;
+        let grid_z = z / self.spacing.y;
+        let last_column = (self.columns - 1) as f32;
+        let last_row = (self.rows - 1) as f32;
+
+        if !(0. ..=last_column).contains(&grid_x) || !(0. ..=last_row).contains(&grid_z) {
+            return None;
+        }
+
+        // The last cell also covers the far edge, a single column or row only its line.
+        let column = (grid_x.floor() as usize).min(self.columns.saturating_sub(2));
+        let row = (grid_z.floor() as usize).min(self.rows.saturating_sub(2));
+        let next_column = (column + 1).min(self.columns - 1);
+        let next_row = (row + 1).min(self.rows - 1);
+        let fraction_x = grid_x - column as f32;
+        let fraction_z = grid_z - row as f32;
+
+        let h00 = self.height(column, row);
+        let h10 = self.height(next_column, row);
+        let h01 = self.height(column, next_row);
+        let h11 = self.height(next_column, next_row);
+
+        let (height, slope_x, slope_z) = if fraction_x + fraction_z <= 1. {
+            (
+                h00 + (h10 - h00) * fraction_x + (h01 - h00) * fraction_z,
+                h10 - h00,
+                h01 - h00,
+            )
+        } else {
+            (
+                h11 + (h01 - h11) * (1. - fraction_x) + (h10 - h11) * (1. - fraction_z),
+                h11 - h01,
+                h11 - h10,
+            )
+        };
+
+        let normal =
+            Vec3::new(-slope_x / self.spacing.x, 1., -slope_z / self.spacing.y).normalize();
+
+        Some((height, normal))
+    }
+
+    /// Normal and distance to the surface, if the center is within radius of it.
+    /// Points below the surface have a negative distance.
+    pub fn contact(&self, center: Vec3, radius: f32) -> Option<(Vec3, f32)> {
+        let (height, normal) = self.surface_at(center.x, center.z)?;
+        let distance = (center.y - height) * normal.y;
+
+        (distance < radius).then(|| (normal, distance))
+    }
+
+    /// First point where the segment between start and end goes below the surface.
+    pub fn raycast(&self, start: Vec3, end: Vec3) -> Option<RayHit> {
+        let above = |fraction: f32| {
+            let point = start.lerp(end, fraction);
+            self.surface_at(point.x, point.z)
+                .map(|(height, _)| height <= point.y)
+        };
+
+        // Steps of at most half a cell, so no cell is skipped.
+        let horizontal = Vec2::new(end.x - start.x, end.z - start.z) / self.spacing;
+        let steps = (horizontal.length() * 2.).ceil().max(1.) as u32;
+        let mut previous = 0.;
+
+        for step in 1..=steps {
+            let fraction = step as f32 / steps as f32;
+
+            if above(previous) == Some(true) && above(fraction) == Some(false) {
+                // Bisection between the last point above and the first point below.
+                let (mut low, mut high) = (previous, fraction);
+
+                for _ in 0..8 {
+                    let middle = (low + high) / 2.;
+
+                    if above(middle) == Some(false) {
+                        high = middle;
+                    } else {
+                        low = middle;
+                    }
+                }
+
+                let point = start.lerp(end, high);
+                let (_, normal) = self.surface_at(point.x, point.z)?;
+
+                return Some(RayHit {
+                    fraction: high,
+                    normal,
+                });
+            }
+
+            previous = fraction;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Slope rising by one per cell along x, with cells of 2 by 1.
+    fn ramp() -> Heightfield {
+        Heightfield::from_fn(4, 3, Vec2::new(2., 1.), |column, _| column as f32)
+    }
+
+    #[test]
+    fn interpolates_the_surface() {
+        let heightfield = ramp();
+        let (height, normal) = heightfield.surface_at(3., 0.5).unwrap();
+
+        assert!((height - 1.5).abs() < 1e-5);
+        assert!((normal - Vec3::new(-1., 2., 0.).normalize()).length() < 1e-5);
+
+        // The far edges belong to the last cell.
+        let (height, _) = heightfield.surface_at(6., 2.).unwrap();
+        assert!((height - 3.).abs() < 1e-5);
+
+        assert!(heightfield.surface_at(-0.1, 1.).is_none());
+        assert!(heightfield.surface_at(6.1, 1.).is_none());
+        assert!(heightfield.surface_at(1., 2.1).is_none());
+    }
+
+    #[test]
+    fn touches_spheres_near_the_surface() {
+        let heightfield = Heightfield::from_fn(3, 3, Vec2::ONE, |_, _| 1.);
+
+        let (normal, distance) = heightfield.contact(Vec3::new(1., 1.25, 1.), 0.5).unwrap();
+        assert_eq!(normal, Vec3::Y);
+        assert!((distance - 0.25).abs() < 1e-5);
+
+        // Sunk below the surface.
+        let (_, distance) = heightfield.contact(Vec3::new(1.5, 0.5, 0.5), 0.5).unwrap();
+        assert!((distance + 0.5).abs() < 1e-5);
+
+        assert!(heightfield.contact(Vec3::new(1., 1.75, 1.), 0.5).is_none());
+        assert!(heightfield.contact(Vec3::new(3., 1., 1.), 0.5).is_none());
+    }
+
+    #[test]
+    fn sweeps_across_cell_edges() {
+        // Flat for two cells, then rising to a step of 2 at the fourth column.
+        let heightfield = Heightfield::from_fn(
+            5,
+            2,
+            Vec2::ONE,
+            |column, _| if column < 3 { 0. } else { 2. },
+        );
+
+        // Flying along x across two cell edges into the slope of the third cell.
+        let hit = heightfield
+            .raycast(Vec3::new(0., 1., 0.5), Vec3::new(4., 1., 0.5))
+            .unwrap();
+
+        // The slope reaches a height of 1 halfway through the cell.
+        let x = hit.fraction * 4.;
+        assert!((x - 2.5).abs() < 0.01, "hit at {}", x);
+        assert!(hit.normal.x < 0.);
+
+        // Passing above the step doesn't hit.
+        assert!(heightfield
+            .raycast(Vec3::new(0., 2.5, 0.5), Vec3::new(4., 2.5, 0.5))
+            .is_none());
+    }
+
+    #[test]
+    fn ignores_rays_outside_the_border() {
+        let heightfield = ramp();
+
+        // Falling next to the heightfield.
+        assert!(heightfield
+            .raycast(Vec3::new(7., 5., 1.), Vec3::new(7., -5., 1.))
+            .is_none());
+
+        // Coming from outside and falling through the edge.
+        let hit = heightfield
+            .raycast(Vec3::new(-2., 2., 1.), Vec3::new(2., 0., 1.))
+            .unwrap();
+        let point = Vec3::new(-2., 2., 1.).lerp(Vec3::new(2., 0., 1.), hit.fraction);
+        assert!((point - Vec3::new(1., 0.5, 1.)).length() < 0.02);
+
+        // Leaving the far border while still above the surface.
+        assert!(heightfield
+            .raycast(Vec3::new(5., 3.5, 1.), Vec3::new(9., 2., 1.))
+            .is_none());
+    }
+
+    #[test]
+    fn supports_single_columns() {
+        let heightfield = Heightfield::from_fn(1, 3, Vec2::ONE, |_, row| row as f32);
+
+        let (height, normal) = heightfield.surface_at(0., 1.5).unwrap();
+        assert!((height - 1.5).abs() < 1e-5);
+        assert!((normal - Vec3::new(0., 1., -1.).normalize()).length() < 1e-5);
+
+        // The line has no width.
+        assert!(heightfield.surface_at(0.1, 1.).is_none());
+
+        let hit = heightfield
+            .raycast(Vec3::new(0., 5., 1.), Vec3::new(0., -5., 1.))
+            .unwrap();
+        assert!((hit.fraction - 0.4).abs() < 0.01);
+
+        let single = Heightfield::new(1, 1, Vec2::ONE, vec![2.]);
+        let (height, normal) = single.surface_at(0., 0.).unwrap();
+        assert_eq!((height, normal), (2., Vec3::Y));
+    }
+}
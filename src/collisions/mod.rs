@@ -0,0 +1,6 @@
+pub mod collider;
+pub mod heightfield;
+pub mod particle_collision;
+pub mod sleep;
+pub mod substeps;
+pub mod triangle_mesh;
@@ -0,0 +1,221 @@
+use super::sleep::Asleep;
+use crate::emitters::emitter::{
+    velocity_to_emitter, velocity_to_world, Emitter, Particle, ParticleAttributes, Velocity,
+};
+use crate::grid::Grid;
+use crate::math::distribution::Distribution;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Overlap that is tolerated, so resting particles don't jitter.
+const PENETRATION_SLOP: f32 = 0.01;
+/// Fraction of the overlap that is resolved per frame.
+const SEPARATION_FRACTION: f32 = 0.8;
+
+/// Opts the particles of an emitter in to collisions with each other.
+#[derive(Debug)]
+pub struct ParticleCollisions {
+    /// number between 0 and 1, (percentage of bounciness).
+    pub elasticity: Distribution<f32>,
+}
+
+#[derive(Debug, Component)]
+pub struct CollisionOptions {
+    pub elasticity: Distribution<f32>,
+}
+
+/// Particles with this component collide with each other.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct Collidable {
+    pub elasticity: f32,
+}
+
+/// Event for every pair of particles that bumped into each other.
+#[derive(Debug, Clone)]
+pub struct ParticlesCollided {
+    pub a: Entity,
+    pub b: Entity,
+    /// Speed at which the particles approached each other.
+    pub impact_speed: f32,
+}
+
+pub struct ParticleCollisionPlugin;
+
+impl Plugin for ParticleCollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ParticlesCollided>()
+            .add_system(collide_particles_system);
+    }
+}
+
+impl From<ParticleCollisions> for CollisionOptions {
+    fn from(options: ParticleCollisions) -> Self {
+        Self {
+            elasticity: options.elasticity,
+        }
+    }
+}
+
+/// World space state of a colliding particle.
+struct Body {
+    position: Vec3,
+    velocity: Vec3,
+    radius: f32,
+    inverse_mass: f32,
+    elasticity: f32,
+}
+
+/// Corrections in world space, summed over all collisions of a particle.
+#[derive(Default)]
+struct Correction {
+    velocity: Vec3,
+    position: Vec3,
+}
+
+fn collide_particles_system(
+    mut particles_query: Query<
+        (
+            Entity,
+            &Parent,
+            &GlobalTransform,
+            &mut Transform,
+            &mut Velocity,
+            &ParticleAttributes,
+            &Collidable,
+            Option<&Asleep>,
+        ),
+        With<Particle>,
+    >,
+    emitter_query: Query<&GlobalTransform, With<Emitter>>,
+    grid: Res<Grid>,
+    mut events: EventWriter<ParticlesCollided>,
+) {
+    let mut bodies = HashMap::default();
+    let mut max_radius: f32 = 0.;
+
+    for (entity, parent, global_transform, _, velocity, attributes, collidable, asleep) in
+        particles_query.iter()
+    {
+        let emitter_transform = match emitter_query.get(parent.0) {
+            Ok(emitter_transform) => emitter_transform,
+            Err(_) => continue,
+        };
+
+        // Particle meshes have a radius of 1, so the scale is the radius.
+        let radius = global_transform.scale.max_element();
+        max_radius = max_radius.max(radius);
+
+        bodies.insert(
+            entity,
+            Body {
+                position: global_transform.translation,
+                velocity: velocity_to_world(emitter_transform, velocity.to_vec3()),
+                radius,
+                // Asleep particles don't move, so other particles can pile up on them.
+                inverse_mass: if asleep.is_some() {
+                    0.
+                } else {
+                    1. / attributes.mass
+                },
+                elasticity: collidable.elasticity,
+            },
+        );
+    }
+
+    if bodies.is_empty() {
+        return;
+    }
+
+    let mut corrections: HashMap<Entity, Correction> = HashMap::default();
+
+    grid.for_each_pair(max_radius * 2., |a, b| {
+        let (a_body, b_body) = match (bodies.get(&a.entity), bodies.get(&b.entity)) {
+            (Some(a_body), Some(b_body)) => (a_body, b_body),
+            _ => return,
+        };
+
+        if let Some((a_correction, b_correction, impact_speed)) = collide(a_body, b_body) {
+            if 0. < impact_speed {
+                events.send(ParticlesCollided {
+                    a: a.entity,
+                    b: b.entity,
+                    impact_speed,
+                });
+            }
+
+            let correction = corrections.entry(a.entity).or_default();
+            correction.velocity += a_correction.velocity;
+            correction.position += a_correction.position;
+
+            let correction = corrections.entry(b.entity).or_default();
+            correction.velocity += b_correction.velocity;
+            correction.position += b_correction.position;
+        }
+    });
+
+    for (entity, correction) in corrections {
+        let (_, parent, _, mut transform, mut velocity, _, _, _) =
+            match particles_query.get_mut(entity) {
+                Ok(particle) => particle,
+                Err(_) => continue,
+            };
+
+        let emitter_transform = match emitter_query.get(parent.0) {
+            Ok(emitter_transform) => emitter_transform,
+            Err(_) => continue,
+        };
+
+        // Particles are children of the emitter, so corrections are converted to emitter space.
+        let new_velocity =
+            velocity.to_vec3() + velocity_to_emitter(emitter_transform, correction.velocity);
+        velocity.set_vec3(new_velocity);
+        transform.translation +=
+            emitter_transform.rotation.inverse() * correction.position / emitter_transform.scale;
+    }
+}
+
+/// Sphere-sphere collision with an impulse along the contact normal.
+/// Overlap is resolved by moving the particles apart, heavier particles move less.
+/// Also returns the speed at which they approached each other.
+fn collide(a: &Body, b: &Body) -> Option<(Correction, Correction, f32)> {
+    let offset = b.position - a.position;
+    let distance = offset.length();
+    let overlap = a.radius + b.radius - distance;
+
+    if overlap <= 0. {
+        return None;
+    }
+
+    let normal = if 0. < distance {
+        offset / distance
+    } else {
+        Vec3::Y
+    };
+    let total_inverse_mass = a.inverse_mass + b.inverse_mass;
+
+    if total_inverse_mass == 0. {
+        return None;
+    }
+
+    let mut a_correction = Correction::default();
+    let mut b_correction = Correction::default();
+
+    // Particles that are already moving apart only get separated.
+    let approach_speed = (a.velocity - b.velocity).dot(normal);
+
+    if 0. < approach_speed {
+        let elasticity = (a.elasticity + b.elasticity) / 2.;
+        let impulse = (1. + elasticity) * approach_speed / total_inverse_mass;
+
+        a_correction.velocity = -normal * impulse * a.inverse_mass;
+        b_correction.velocity = normal * impulse * b.inverse_mass;
+    }
+
+    let separation =
+        (overlap - PENETRATION_SLOP).max(0.) * SEPARATION_FRACTION / total_inverse_mass;
+
+    a_correction.position = -normal * separation * a.inverse_mass;
+    b_correction.position = normal * separation * b.inverse_mass;
+
+    Some((a_correction, b_correction, approach_speed.max(0.)))
+}
@@ -0,0 +1,133 @@
+use super::collider::ColliderSystem;
+use super::particle_collision::ParticlesCollided;
+use crate::emitters::emitter::{AngularVelocity, Emitter, LifeCycle, Particle, Velocity};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Lets particles of an emitter fall asleep when they rest on a collider or on other particles,
+/// so piles of sand, snow or debris are cheap. Asleep particles are not moved by forces.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct SleepOptions {
+    /// Particles slower than this fall asleep, faster impacts wake them up.
+    pub speed_threshold: f32,
+    /// How long a particle has to rest before it falls asleep.
+    pub sleep_after_ms: u32,
+    /// Asleep particles don't age.
+    pub pause_lifetime: bool,
+}
+
+/// Tracks since when a particle of an emitter with SleepOptions is resting.
+#[derive(Debug, Component, Default)]
+pub struct RestTimer {
+    resting_since_ms: Option<u128>,
+    /// Set by the collider system, resting contacts are too slow for a ParticleCollided.
+    touched_collider: bool,
+}
+
+impl RestTimer {
+    pub fn touch_collider(&mut self) {
+        self.touched_collider = true;
+    }
+}
+
+#[derive(Debug, Component)]
+pub struct Asleep {
+    last_update_ms: u128,
+}
+
+pub struct SleepPlugin;
+
+impl Plugin for SleepPlugin {
+    fn build(&self, app: &mut App) {
+        // Touches and impacts of this frame are known after the collisions.
+        app.add_system(update_sleep_system.after(ColliderSystem::Collide));
+    }
+}
+
+fn update_sleep_system(
+    mut particles_query: Query<
+        (
+            Entity,
+            &Parent,
+            &mut Velocity,
+            &mut AngularVelocity,
+            &mut LifeCycle,
+            &mut RestTimer,
+            Option<&mut Asleep>,
+        ),
+        With<Particle>,
+    >,
+    emitter_query: Query<&SleepOptions, With<Emitter>>,
+    mut particle_events: EventReader<ParticlesCollided>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    let total_elapsed_ms = time.time_since_startup().as_millis();
+
+    // Particles that touched another particle, with their fastest impact.
+    let mut impacts: HashMap<Entity, f32> = HashMap::default();
+    let mut add_impact = |entity: Entity, impact_speed: f32| {
+        let fastest = impacts.entry(entity).or_insert(0.);
+        *fastest = fastest.max(impact_speed);
+    };
+
+    for event in particle_events.iter() {
+        add_impact(event.a, event.impact_speed);
+        add_impact(event.b, event.impact_speed);
+    }
+
+    for (
+        entity,
+        parent,
+        mut velocity,
+        mut angular_velocity,
+        mut life_cycle,
+        mut rest_timer,
+        asleep,
+    ) in particles_query.iter_mut()
+    {
+        let options = match emitter_query.get(parent.0) {
+            Ok(options) => options,
+            Err(_) => continue,
+        };
+
+        let speed = velocity.to_vec3().length();
+        // Colliders count as an impact without speed.
+        if std::mem::take(&mut rest_timer.touched_collider) {
+            impacts.entry(entity).or_insert(0.);
+        }
+
+        let impact_speed = impacts.get(&entity).copied();
+
+        if let Some(mut asleep) = asleep {
+            // Impulses give asleep particles velocity, collisions only send events.
+            if options.speed_threshold < speed.max(impact_speed.unwrap_or(0.)) {
+                commands.entity(entity).remove::<Asleep>();
+                rest_timer.resting_since_ms = None;
+                continue;
+            }
+
+            if options.pause_lifetime {
+                life_cycle.delay(total_elapsed_ms - asleep.last_update_ms);
+            }
+
+            asleep.last_update_ms = total_elapsed_ms;
+            continue;
+        }
+
+        if impact_speed.is_none() || options.speed_threshold <= speed {
+            rest_timer.resting_since_ms = None;
+            continue;
+        }
+
+        let resting_since_ms = *rest_timer.resting_since_ms.get_or_insert(total_elapsed_ms);
+
+        if options.sleep_after_ms as u128 <= total_elapsed_ms - resting_since_ms {
+            velocity.set_vec3(Vec3::ZERO);
+            angular_velocity.set_vec3(Vec3::ZERO);
+            commands.entity(entity).insert(Asleep {
+                last_update_ms: total_elapsed_ms,
+            });
+        }
+    }
+}
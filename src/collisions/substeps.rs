@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+
+/// Splits the movement of particles per frame in smaller steps, each resolved against the
+/// Colliders, so fast particles don't skip over thin colliders.
+#[derive(Debug, Component, Clone, Copy)]
+pub enum Substeps {
+    Fixed(u32),
+    /// Particles move at most max_radii times their radius per substep, e.g. 1.
+    Adaptive {
+        max_radii: f32,
+        max_substeps: u32,
+    },
+}
+
+/// Particle of an emitter with Substeps, moved by the collision stage.
+#[derive(Debug, Component)]
+pub struct Substepped;
+
+impl Substeps {
+    /// Substeps for a particle that travels distance this frame.
+    pub fn count(&self, distance: f32, radius: f32) -> u32 {
+        match *self {
+            Substeps::Fixed(count) => count.max(1),
+            Substeps::Adaptive {
+                max_radii,
+                max_substeps,
+            } => {
+                let max_step = (radius * max_radii).max(f32::EPSILON);
+                ((distance / max_step).ceil() as u32).clamp(1, max_substeps.max(1))
+            }
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use std::cell::Cell;
+
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+/// Static triangle soup with a bounding volume hierarchy, e.g. level geometry.
+#[derive(Debug)]
+pub struct TriangleMesh {
+    /// Ordered so every leaf node is a range of triangles.
+    triangles: Vec<Triangle>,
+    /// The first node is the root.
+    nodes: Vec<BvhNode>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Triangle {
+    a: Vec3,
+    b: Vec3,
+    c: Vec3,
+}
+
+#[derive(Debug)]
+struct BvhNode {
+    min: Vec3,
+    max: Vec3,
+    kind: NodeKind,
+}
+
+#[derive(Debug)]
+enum NodeKind {
+    Leaf { start: usize, end: usize },
+    Branch { left: usize, right: usize },
+}
+
+/// Hit of a ray from a start to an end point.
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    /// Fraction of the way between start and end.
+    pub fraction: f32,
+    /// Surface normal, facing the start of the ray.
+    pub normal: Vec3,
+}
+
+impl TriangleMesh {
+    pub fn new(triangles: Vec<[Vec3; 3]>) -> Self {
+        let mut triangles: Vec<Triangle> = triangles
+            .into_iter()
+            .map(|[a, b, c]| Triangle { a, b, c })
+            .collect();
+        let mut nodes = Vec::new();
+
+        if !triangles.is_empty() {
+            build_node(&mut triangles, 0, &mut nodes);
+        }
+
+        Self { triangles, nodes }
+    }
+
+    /// Triangles of a Mesh, None for other topologies than TriangleList or without positions.
+    pub fn from_mesh(mesh: &Mesh) -> Option<Self> {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return None;
+        }
+
+        let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION)? {
+            VertexAttributeValues::Float32x3(positions) => positions,
+            _ => return None,
+        };
+
+        let indices: Vec<usize> = match mesh.indices() {
+            Some(Indices::U16(indices)) => indices.iter().map(|i| *i as usize).collect(),
+            Some(Indices::U32(indices)) => indices.iter().map(|i| *i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+
+        let triangles = indices
+            .chunks_exact(3)
+            .map(|corners| {
+                [
+                    Vec3::from(positions[corners[0]]),
+                    Vec3::from(positions[corners[1]]),
+                    Vec3::from(positions[corners[2]]),
+                ]
+            })
+            .collect();
+
+        Some(Self::new(triangles))
+    }
+
+    /// Normal and distance to the closest triangle within radius of the center.
+    /// Meshes have no inside, the normal points from the surface to the center.
+    pub fn closest(&self, center: Vec3, radius: f32) -> Option<(Vec3, f32)> {
+        let min = center - Vec3::splat(radius);
+        let max = center + Vec3::splat(radius);
+        let mut closest: Option<(Vec3, f32)> = None;
+
+        self.visit(
+            |node| min.cmple(node.max).all() && node.min.cmple(max).all(),
+            |triangle| {
+                let offset = center - triangle.closest_point(center);
+                let distance = offset.length();
+
+                if radius <= distance || closest.map_or(false, |(_, best)| best <= distance) {
+                    return;
+                }
+
+                let normal = if 0. < distance {
+                    offset / distance
+                } else {
+                    triangle.normal()
+                };
+
+                closest = Some((normal, distance));
+            },
+        );
+
+        closest
+    }
+
+    /// First triangle on the segment between start and end.
+    pub fn raycast(&self, start: Vec3, end: Vec3) -> Option<RayHit> {
+        let direction = end - start;
+        let inverse_direction = Vec3::ONE / direction;
+        // Read while entering nodes and written per triangle.
+        let hit: Cell<Option<RayHit>> = Cell::new(None);
+
+        self.visit(
+            |node| {
+                let max_fraction = hit.get().map_or(1., |hit| hit.fraction);
+                ray_hits_box(start, inverse_direction, node, max_fraction)
+            },
+            |triangle| {
+                let fraction = match triangle.raycast(start, direction) {
+                    Some(fraction) => fraction,
+                    None => return,
+                };
+
+                if hit.get().map_or(false, |hit| hit.fraction <= fraction) {
+                    return;
+                }
+
+                let normal = triangle.normal();
+                let normal = if 0. < normal.dot(direction) {
+                    -normal
+                } else {
+                    normal
+                };
+
+                hit.set(Some(RayHit { fraction, normal }));
+            },
+        );
+
+        hit.get()
+    }
+
+    /// Calls on_triangle for the triangles of all leaf nodes for which enter_node holds.
+    fn visit(
+        &self,
+        mut enter_node: impl FnMut(&BvhNode) -> bool,
+        mut on_triangle: impl FnMut(&Triangle),
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+
+            if !enter_node(node) {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Leaf { start, end } => {
+                    for triangle in self.triangles[start..end].iter() {
+                        on_triangle(triangle);
+                    }
+                }
+                NodeKind::Branch { left, right } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+    }
+}
+
+/// Splits the triangles at the median of the longest axis, returns the index of the node.
+fn build_node(triangles: &mut [Triangle], offset: usize, nodes: &mut Vec<BvhNode>) -> usize {
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    let mut centroid_min = Vec3::splat(f32::MAX);
+    let mut centroid_max = Vec3::splat(f32::MIN);
+
+    for triangle in triangles.iter() {
+        min = min.min(triangle.a).min(triangle.b).min(triangle.c);
+        max = max.max(triangle.a).max(triangle.b).max(triangle.c);
+        centroid_min = centroid_min.min(triangle.centroid());
+        centroid_max = centroid_max.max(triangle.centroid());
+    }
+
+    let index = nodes.len();
+    nodes.push(BvhNode {
+        min,
+        max,
+        kind: NodeKind::Leaf {
+            start: offset,
+            end: offset + triangles.len(),
+        },
+    });
+
+    if triangles.len() <= MAX_LEAF_TRIANGLES {
+        return index;
+    }
+
+    let extent = centroid_max - centroid_min;
+    let axis = if extent.y <= extent.x && extent.z <= extent.x {
+        0
+    } else if extent.z <= extent.y {
+        1
+    } else {
+        2
+    };
+
+    let middle = triangles.len() / 2;
+    triangles.select_nth_unstable_by(middle, |a, b| {
+        a.centroid()[axis]
+            .partial_cmp(&b.centroid()[axis])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let (left_triangles, right_triangles) = triangles.split_at_mut(middle);
+    let left = build_node(left_triangles, offset, nodes);
+    let right = build_node(right_triangles, offset + middle, nodes);
+    nodes[index].kind = NodeKind::Branch { left, right };
+
+    index
+}
+
+/// Slab test of a ray against the bounds of a node.
+fn ray_hits_box(start: Vec3, inverse_direction: Vec3, node: &BvhNode, max_fraction: f32) -> bool {
+    let to_min = (node.min - start) * inverse_direction;
+    let to_max = (node.max - start) * inverse_direction;
+    let enter = to_min.min(to_max).max_element();
+    let exit = to_min.max(to_max).min_element();
+
+    enter <= exit && 0. <= exit && enter <= max_fraction
+}
+
+impl Triangle {
+    fn centroid(&self) -> Vec3 {
+        (self.a + self.b + self.c) / 3.
+    }
+
+    fn normal(&self) -> Vec3 {
+        (self.b - self.a).cross(self.c - self.a).normalize_or_zero()
+    }
+
+    /// Möller–Trumbore intersection, both sides of the triangle are hit.
+    fn raycast(&self, start: Vec3, direction: Vec3) -> Option<f32> {
+        let edge_1 = self.b - self.a;
+        let edge_2 = self.c - self.a;
+        let p = direction.cross(edge_2);
+        let determinant = edge_1.dot(p);
+
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let inverse_determinant = 1. / determinant;
+        let to_start = start - self.a;
+        let u = to_start.dot(p) * inverse_determinant;
+
+        if !(0. ..=1.).contains(&u) {
+            return None;
+        }
+
+        let q = to_start.cross(edge_1);
+        let v = direction.dot(q) * inverse_determinant;
+
+        if v < 0. || 1. < u + v {
+            return None;
+        }
+
+        let fraction = edge_2.dot(q) * inverse_determinant;
+        (0. ..=1.).contains(&fraction).then(|| fraction)
+    }
+
+    /// Closest point on the triangle, from Real-Time Collision Detection by Christer Ericson.
+    fn closest_point(&self, point: Vec3) -> Vec3 {
+        let Triangle { a, b, c } = *self;
+        let ab = b - a;
+        let ac = c - a;
+        let ap = point - a;
+        let d1 = ab.dot(ap);
+        let d2 = ac.dot(ap);
+
+        if d1 <= 0. && d2 <= 0. {
+            return a;
+        }
+
+        let bp = point - b;
+        let d3 = ab.dot(bp);
+        let d4 = ac.dot(bp);
+
+        if 0. <= d3 && d4 <= d3 {
+            return b;
+        }
+
+        let vc = d1 * d4 - d3 * d2;
+
+        if vc <= 0. && 0. <= d1 && d3 <= 0. {
+            return a + ab * (d1 / (d1 - d3));
+        }
+
+        let cp = point - c;
+        let d5 = ab.dot(cp);
+        let d6 = ac.dot(cp);
+
+        if 0. <= d6 && d5 <= d6 {
+            return c;
+        }
+
+        let vb = d5 * d2 - d1 * d6;
+
+        if vb <= 0. && 0. <= d2 && d6 <= 0. {
+            return a + ac * (d2 / (d2 - d6));
+        }
+
+        let va = d3 * d6 - d5 * d4;
+
+        if va <= 0. && 0. <= d4 - d3 && 0. <= d5 - d6 {
+            return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+        }
+
+        let denominator = 1. / (va + vb + vc);
+        a + ab * (vb * denominator) + ac * (vc * denominator)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Flat grid of quads in the xz plane from 0 to size, two triangles per quad.
+    fn floor(size: usize) -> TriangleMesh {
+        let mut triangles = Vec::new();
+
+        for x in 0..size {
+            for z in 0..size {
+                let corner = |dx: usize, dz: usize| Vec3::new((x + dx) as f32, 0., (z + dz) as f32);
+                triangles.push([corner(0, 0), corner(1, 0), corner(1, 1)]);
+                triangles.push([corner(0, 0), corner(1, 1), corner(0, 1)]);
+            }
+        }
+
+        TriangleMesh::new(triangles)
+    }
+
+    #[test]
+    fn builds_a_bvh_over_all_triangles() {
+        let mesh = floor(8);
+        assert_eq!(mesh.triangles.len(), 128);
+
+        let mut leaf_triangles = 0;
+
+        for node in mesh.nodes.iter() {
+            match node.kind {
+                NodeKind::Leaf { start, end } => {
+                    assert!(end - start <= MAX_LEAF_TRIANGLES);
+
+                    // Every triangle is inside the bounds of its leaf.
+                    for triangle in mesh.triangles[start..end].iter() {
+                        for corner in [triangle.a, triangle.b, triangle.c] {
+                            assert!(node.min.cmple(corner).all() && corner.cmple(node.max).all());
+                        }
+                    }
+
+                    leaf_triangles += end - start;
+                }
+                NodeKind::Branch { left, right } => {
+                    for child in [&mesh.nodes[left], &mesh.nodes[right]] {
+                        assert!(node.min.cmple(child.min).all() && child.max.cmple(node.max).all());
+                    }
+                }
+            }
+        }
+
+        assert_eq!(leaf_triangles, 128);
+        assert!(TriangleMesh::new(Vec::new())
+            .raycast(Vec3::Y, -Vec3::Y)
+            .is_none());
+    }
+
+    #[test]
+    fn raycasts_the_first_triangle() {
+        let mesh = floor(8);
+
+        let hit = mesh
+            .raycast(Vec3::new(2.5, 1., 3.5), Vec3::new(2.5, -1., 3.5))
+            .unwrap();
+        assert!((hit.fraction - 0.5).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec3::Y);
+
+        // Both sides are hit, the normal faces the start.
+        let hit = mesh
+            .raycast(Vec3::new(6.2, -2., 1.7), Vec3::new(6.2, 2., 1.7))
+            .unwrap();
+        assert!((hit.fraction - 0.5).abs() < 1e-5);
+        assert_eq!(hit.normal, -Vec3::Y);
+
+        assert!(mesh
+            .raycast(Vec3::new(2.5, 1., 3.5), Vec3::new(2.5, 0.5, 3.5))
+            .is_none());
+        assert!(mesh
+            .raycast(Vec3::new(-1., 1., 3.5), Vec3::new(-1., -1., 3.5))
+            .is_none());
+    }
+
+    #[test]
+    fn finds_the_closest_triangle() {
+        let mesh = floor(8);
+
+        let (normal, distance) = mesh.closest(Vec3::new(4.3, 0.25, 4.6), 0.5).unwrap();
+        assert_eq!(normal, Vec3::Y);
+        assert!((distance - 0.25).abs() < 1e-5);
+
+        // Past the edge the closest point is on the edge.
+        let (normal, distance) = mesh.closest(Vec3::new(-0.3, 0.4, 2.), 1.).unwrap();
+        assert!((normal - Vec3::new(-0.6, 0.8, 0.)).length() < 1e-5);
+        assert!((distance - 0.5).abs() < 1e-5);
+
+        assert!(mesh.closest(Vec3::new(4.3, 0.5, 4.6), 0.5).is_none());
+    }
+
+    #[test]
+    fn only_takes_triangle_lists() {
+        let positions = vec![[0., 0., 0.], [1., 0., 0.], [0., 0., 1.]];
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions.clone());
+        assert_eq!(TriangleMesh::from_mesh(&mesh).unwrap().triangles.len(), 1);
+
+        let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        assert!(TriangleMesh::from_mesh(&mesh).is_none());
+    }
+}
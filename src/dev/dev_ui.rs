@@ -2,6 +2,7 @@ use bevy::diagnostic::Diagnostics;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::prelude::*;
 
+use crate::collisions::collider::CollisionMetrics;
 use crate::emitters::emitter::Particle;
 use crate::emitters::emitter::Velocity;
 
@@ -14,6 +15,7 @@ impl Plugin for DevUIPlugin {
             .add_system(update_fps_text)
             .add_system(update_frame_time_text)
             .add_system(update_particle_count_text)
+            .add_system(update_collision_text)
             .add_plugin(FrameTimeDiagnosticsPlugin);
     }
 }
@@ -23,6 +25,8 @@ struct Metric {
     frame_time: f64,
     last_updated_ms: u128,
     particle_count: usize,
+    substeps: u32,
+    collision_time: f64,
 }
 
 #[derive(Component)]
@@ -34,6 +38,9 @@ struct FPSText;
 #[derive(Component)]
 struct ParticleCountText;
 
+#[derive(Component)]
+struct CollisionText;
+
 fn setup(mut commands: Commands, asset_server: Res<AssetServer>, time: Res<Time>) {
     // FPS
     commands
@@ -145,11 +152,49 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>, time: Res<Time>
         })
         .insert(ParticleCountText);
 
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text {
+                sections: vec![
+                    TextSection {
+                        value: "Collisions: ".to_string(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 20.0,
+                            color: Color::rgb(1., 1., 1.),
+                        },
+                    },
+                    TextSection {
+                        value: "".to_string(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 20.0,
+                            color: Color::rgb(1., 1., 1.),
+                        },
+                    },
+                ],
+                ..Default::default()
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(65.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(CollisionText);
+
     commands.insert_resource(Metric {
         frame_time: -1.,
         fps: -1.,
         last_updated_ms: time.time_since_startup().as_millis(),
         particle_count: 0,
+        substeps: 0,
+        collision_time: -1.,
     })
 }
 
@@ -174,6 +219,18 @@ fn update_particle_count_text(
     text.sections[1].value = format!("{}", metrics.particle_count);
 }
 
+fn update_collision_text(
+    mut query: Query<&mut Text, With<CollisionText>>,
+    metrics: ResMut<Metric>,
+) {
+    let mut text = query.single_mut();
+    text.sections[1].value = format!(
+        "{} substeps, {}",
+        metrics.substeps,
+        convert(metrics.collision_time, 6)
+    );
+}
+
 fn convert(val: f64, precision: usize) -> String {
     format!("{:.precision$}", val)
 }
@@ -184,6 +241,8 @@ fn update_metrics(
     mut metrics: ResMut<Metric>,
     particle_query: Query<&Velocity, With<Particle>>,
     diagnostics: ResMut<'_, Diagnostics>,
+    // Only there with the ColliderPlugin.
+    collision_metrics: Option<Res<CollisionMetrics>>,
     time: Res<Time>,
 ) {
     let total_elapsed_ms = time.time_since_startup().as_millis();
@@ -209,4 +268,9 @@ fn update_metrics(
     metrics.frame_time = frame_time;
     metrics.last_updated_ms = total_elapsed_ms;
     metrics.particle_count = particle_query.iter().count();
+
+    if let Some(collision_metrics) = collision_metrics {
+        metrics.substeps = collision_metrics.substeps;
+        metrics.collision_time = collision_metrics.duration.as_secs_f64();
+    }
 }
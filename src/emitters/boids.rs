@@ -0,0 +1,423 @@
+use crate::collisions::sleep::Asleep;
+use crate::emitters::bounds::BoundsShape;
+use crate::emitters::emitter::{
+    velocity_to_emitter, velocity_to_world, Emitter, EmitterSystem, Particle, ParticleAttributes,
+    Velocity,
+};
+use crate::grid::Grid;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Flocking of the particles of an emitter, based on Craig Reynolds' boids.
+/// Only particles of the same emitter are neighbors, everything is in world space.
+#[derive(Debug, Component, Clone)]
+pub struct Boids {
+    /// Steers away from neighbors that are too close.
+    pub separation_weight: f32,
+    /// Steers towards the average direction of the neighbors.
+    pub alignment_weight: f32,
+    /// Steers towards the average position of the neighbors.
+    pub cohesion_weight: f32,
+    /// Neighbors further away are ignored.
+    pub perception_radius: f32,
+    /// Neighbors closer than this are avoided.
+    pub separation_radius: f32,
+    /// Full angle of the view cone around the direction of flight, 360 sees everything.
+    pub field_of_view_degrees: f32,
+    /// Units per second.
+    pub max_speed: f32,
+    /// In newton, lighter particles turn faster.
+    pub max_steering_force: f32,
+    pub seek: Option<BoidTarget>,
+    pub flee: Option<BoidTarget>,
+    pub stay_inside: Option<StayInside>,
+}
+
+/// Steering towards or away from the GlobalTransform of an entity, e.g. a player or predator.
+#[derive(Debug, Clone, Copy)]
+pub struct BoidTarget {
+    pub entity: Entity,
+    pub weight: f32,
+    /// Only steers within this distance, None for any distance.
+    pub radius: Option<f32>,
+}
+
+/// Steers back when a particle comes within margin of the edge of the shape.
+#[derive(Debug, Clone, Copy)]
+pub struct StayInside {
+    /// In world space.
+    pub shape: BoundsShape,
+    pub margin: f32,
+    pub weight: f32,
+}
+
+impl Boids {
+    pub fn new(perception_radius: f32, max_speed: f32, max_steering_force: f32) -> Self {
+        Self {
+            separation_weight: 1.5,
+            alignment_weight: 1.,
+            cohesion_weight: 1.,
+            perception_radius,
+            separation_radius: perception_radius / 2.,
+            field_of_view_degrees: 270.,
+            max_speed,
+            max_steering_force,
+            seek: None,
+            flee: None,
+            stay_inside: None,
+        }
+    }
+
+    /// Steering force towards the desired direction, at full speed.
+    fn steer(&self, direction: Vec3, velocity: Vec3) -> Vec3 {
+        if direction == Vec3::ZERO {
+            return Vec3::ZERO;
+        }
+
+        let desired = direction.normalize() * self.max_speed;
+        (desired - velocity).clamp_length_max(self.max_steering_force)
+    }
+
+    fn sees(&self, boid: &Boid, offset: Vec3) -> bool {
+        if 360. <= self.field_of_view_degrees || boid.velocity == Vec3::ZERO {
+            return true;
+        }
+
+        let half_fov = (self.field_of_view_degrees / 2.).to_radians();
+        boid.velocity.angle_between(offset) <= half_fov
+    }
+}
+
+impl StayInside {
+    /// Direction back inside, zero when the position is far enough from the edge.
+    fn direction(&self, position: Vec3) -> Vec3 {
+        match self.shape {
+            BoundsShape::Box { min, max } => {
+                let mut direction = Vec3::ZERO;
+
+                for axis in 0..3 {
+                    if position[axis] < min[axis] + self.margin {
+                        direction[axis] = 1.;
+                    } else if max[axis] - self.margin < position[axis] {
+                        direction[axis] = -1.;
+                    }
+                }
+
+                direction
+            }
+            BoundsShape::Sphere { center, radius } => {
+                let offset = position - center;
+
+                if offset.length() < radius - self.margin {
+                    Vec3::ZERO
+                } else {
+                    -offset
+                }
+            }
+            BoundsShape::Cylinder {
+                center,
+                radius,
+                half_height,
+            } => {
+                let offset = position - center;
+                let radial = Vec3::new(offset.x, 0., offset.z);
+                let mut direction = Vec3::ZERO;
+
+                if radius - self.margin <= radial.length() {
+                    direction -= radial.normalize_or_zero();
+                }
+
+                if offset.y < -half_height + self.margin {
+                    direction.y = 1.;
+                } else if half_height - self.margin < offset.y {
+                    direction.y = -1.;
+                }
+
+                direction
+            }
+        }
+    }
+}
+
+pub struct BoidsPlugin;
+
+impl Plugin for BoidsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(apply_boids_system.before(EmitterSystem::Move));
+    }
+}
+
+/// World space state of a flocking particle.
+struct Boid<'a> {
+    entity: Entity,
+    emitter: Entity,
+    boids: &'a Boids,
+    /// To convert the velocity back to emitter space.
+    emitter_transform: GlobalTransform,
+    position: Vec3,
+    velocity: Vec3,
+}
+
+/// Sums over the visible neighbors of a boid.
+#[derive(Default)]
+struct Neighborhood {
+    separation: Vec3,
+    velocity: Vec3,
+    position: Vec3,
+    count: u32,
+}
+
+impl Neighborhood {
+    fn add(&mut self, boid: &Boid, neighbor: &Boid) {
+        let boids = boid.boids;
+        let offset = neighbor.position - boid.position;
+        let distance = offset.length();
+
+        if boids.perception_radius < distance || !boids.sees(boid, offset) {
+            return;
+        }
+
+        // Closer neighbors push harder.
+        if 0. < distance && distance < boids.separation_radius {
+            self.separation -= offset / distance.powi(2);
+        }
+
+        self.velocity += neighbor.velocity;
+        self.position += neighbor.position;
+        self.count += 1;
+    }
+}
+
+fn apply_boids_system(
+    mut particles_query: Query<
+        (
+            Entity,
+            &Parent,
+            &Transform,
+            &mut Velocity,
+            &ParticleAttributes,
+        ),
+        (With<Particle>, Without<Asleep>),
+    >,
+    emitter_query: Query<(&Boids, &GlobalTransform), With<Emitter>>,
+    target_query: Query<&GlobalTransform>,
+    time: Res<Time>,
+) {
+    let mut flock = Vec::new();
+    let mut indices = HashMap::default();
+    let mut max_perception_radius: f32 = 0.;
+
+    for (entity, parent, transform, velocity, _) in particles_query.iter() {
+        let (boids, emitter_transform) = match emitter_query.get(parent.0) {
+            Ok(emitter) => emitter,
+            Err(_) => continue,
+        };
+
+        max_perception_radius = max_perception_radius.max(boids.perception_radius);
+
+        indices.insert(entity, flock.len());
+        flock.push(Boid {
+            entity,
+            emitter: parent.0,
+            boids,
+            emitter_transform: *emitter_transform,
+            // The GlobalTransform of the particle lags a frame behind and is unset when new.
+            position: emitter_transform.mul_vec3(transform.translation),
+            velocity: velocity_to_world(emitter_transform, velocity.to_vec3()),
+        });
+    }
+
+    if flock.is_empty() {
+        return;
+    }
+
+    // Own grid with cells of the perception radius, the shared grid is sized for collisions
+    // and would visit many cells per boid.
+    let mut grid = Grid::new(max_perception_radius.max(f32::EPSILON));
+    for boid in flock.iter() {
+        grid.insert(boid.entity, boid.position);
+    }
+
+    let mut neighborhoods: Vec<Neighborhood> = flock.iter().map(|_| Default::default()).collect();
+
+    grid.for_each_pair(max_perception_radius, |a, b| {
+        let (a_index, b_index) = (indices[&a.entity], indices[&b.entity]);
+        let (a_boid, b_boid) = (&flock[a_index], &flock[b_index]);
+
+        if a_boid.emitter != b_boid.emitter {
+            return;
+        }
+
+        neighborhoods[a_index].add(a_boid, b_boid);
+        neighborhoods[b_index].add(b_boid, a_boid);
+    });
+
+    let delta_seconds = time.delta_seconds();
+
+    for (boid, neighborhood) in flock.iter().zip(neighborhoods) {
+        let boids = boid.boids;
+        let mut force = Vec3::ZERO;
+
+        if 0 < neighborhood.count {
+            let count = neighborhood.count as f32;
+            force += boids.steer(neighborhood.separation, boid.velocity) * boids.separation_weight;
+            force +=
+                boids.steer(neighborhood.velocity / count, boid.velocity) * boids.alignment_weight;
+            force += boids.steer(neighborhood.position / count - boid.position, boid.velocity)
+                * boids.cohesion_weight;
+        }
+
+        if let Some(seek) = boids.seek {
+            if let Some(offset) = target_offset(&target_query, &seek, boid.position) {
+                force += boids.steer(offset, boid.velocity) * seek.weight;
+            }
+        }
+
+        if let Some(flee) = boids.flee {
+            if let Some(offset) = target_offset(&target_query, &flee, boid.position) {
+                force += boids.steer(-offset, boid.velocity) * flee.weight;
+            }
+        }
+
+        if let Some(stay_inside) = boids.stay_inside {
+            let direction = stay_inside.direction(boid.position);
+            force += boids.steer(direction, boid.velocity) * stay_inside.weight;
+        }
+
+        let (_, _, _, mut velocity, attributes) = match particles_query.get_mut(boid.entity) {
+            Ok(particle) => particle,
+            Err(_) => continue,
+        };
+
+        let new_velocity = (boid.velocity + force / attributes.mass * delta_seconds)
+            .clamp_length_max(boids.max_speed);
+
+        // Particles are children of the emitter, so the velocity is converted to emitter space.
+        velocity.set_vec3(velocity_to_emitter(&boid.emitter_transform, new_velocity));
+    }
+}
+
+/// Offset from the position to the target, None when it is gone or out of range.
+fn target_offset(
+    target_query: &Query<&GlobalTransform>,
+    target: &BoidTarget,
+    position: Vec3,
+) -> Option<Vec3> {
+    let target_transform = target_query.get(target.entity).ok()?;
+    let offset = target_transform.translation - position;
+
+    match target.radius {
+        Some(radius) if radius < offset.length() => None,
+        _ => Some(offset),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn boid<'a>(boids: &'a Boids, world: &mut World, position: Vec3, velocity: Vec3) -> Boid<'a> {
+        Boid {
+            entity: world.spawn().id(),
+            emitter: world.spawn().id(),
+            boids,
+            emitter_transform: GlobalTransform::identity(),
+            position,
+            velocity,
+        }
+    }
+
+    #[test]
+    fn adds_visible_neighbors() {
+        let mut world = World::new();
+        let boids = Boids::new(4., 10., 1.);
+        let center = boid(&boids, &mut world, Vec3::ZERO, Vec3::X);
+        let mut neighborhood = Neighborhood::default();
+
+        // Within the separation radius.
+        let close = boid(&boids, &mut world, Vec3::new(1., 0., 0.), Vec3::Y);
+        neighborhood.add(&center, &close);
+        assert_eq!(1, neighborhood.count);
+        assert_eq!(Vec3::new(-1., 0., 0.), neighborhood.separation);
+
+        // Outside the separation radius, only aligns and attracts.
+        let near = boid(&boids, &mut world, Vec3::new(0., 3., 0.), Vec3::Z);
+        neighborhood.add(&center, &near);
+        assert_eq!(2, neighborhood.count);
+        assert_eq!(Vec3::new(-1., 0., 0.), neighborhood.separation);
+        assert_eq!(Vec3::new(0., 1., 1.), neighborhood.velocity);
+        assert_eq!(Vec3::new(1., 3., 0.), neighborhood.position);
+
+        // Out of the perception radius and behind the boid.
+        let far = boid(&boids, &mut world, Vec3::new(5., 0., 0.), Vec3::X);
+        let behind = boid(&boids, &mut world, Vec3::new(-1., 0., 0.), Vec3::X);
+        neighborhood.add(&center, &far);
+        neighborhood.add(&center, &behind);
+        assert_eq!(2, neighborhood.count);
+    }
+
+    #[test]
+    fn sees_within_the_field_of_view() {
+        let mut world = World::new();
+        let boids = Boids {
+            field_of_view_degrees: 90.,
+            ..Boids::new(4., 10., 1.)
+        };
+
+        let moving = boid(&boids, &mut world, Vec3::ZERO, Vec3::X);
+        assert!(boids.sees(&moving, Vec3::new(1., 0.8, 0.)));
+        assert!(!boids.sees(&moving, Vec3::new(1., 1.2, 0.)));
+        assert!(!boids.sees(&moving, -Vec3::X));
+
+        // Standing still looks everywhere.
+        let standing = boid(&boids, &mut world, Vec3::ZERO, Vec3::ZERO);
+        assert!(boids.sees(&standing, -Vec3::X));
+
+        let all_around = Boids {
+            field_of_view_degrees: 360.,
+            ..Boids::new(4., 10., 1.)
+        };
+        assert!(all_around.sees(&moving, -Vec3::X));
+    }
+
+    #[test]
+    fn steers_back_inside() {
+        let stay_inside = |shape| StayInside {
+            shape,
+            margin: 1.,
+            weight: 1.,
+        };
+
+        let cube = stay_inside(BoundsShape::Box {
+            min: Vec3::splat(-5.),
+            max: Vec3::splat(5.),
+        });
+        assert_eq!(Vec3::ZERO, cube.direction(Vec3::new(3., -3., 0.)));
+        assert_eq!(
+            Vec3::new(-1., 0., 1.),
+            cube.direction(Vec3::new(4.5, 0., -4.5))
+        );
+
+        let sphere = stay_inside(BoundsShape::Sphere {
+            center: Vec3::Y,
+            radius: 5.,
+        });
+        assert_eq!(Vec3::ZERO, sphere.direction(Vec3::new(3., 1., 0.)));
+        assert_eq!(
+            Vec3::new(-4.5, 0., 0.),
+            sphere.direction(Vec3::new(4.5, 1., 0.))
+        );
+
+        let cylinder = stay_inside(BoundsShape::Cylinder {
+            center: Vec3::ZERO,
+            radius: 5.,
+            half_height: 2.,
+        });
+        assert_eq!(Vec3::ZERO, cylinder.direction(Vec3::new(2., 0.5, 2.)));
+        assert_eq!(
+            Vec3::new(-1., -1., 0.),
+            cylinder.direction(Vec3::new(4.5, 1.5, 0.))
+        );
+        assert_eq!(Vec3::Y, cylinder.direction(Vec3::new(0., -1.5, 0.)));
+    }
+}
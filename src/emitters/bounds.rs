@@ -0,0 +1,266 @@
+use super::emitter::{velocity_to_emitter, velocity_to_world, Dead, Emitter, Particle, Velocity};
+use bevy::prelude::*;
+
+/// Keeps the particles of an emitter inside a shape, particles are inside when their
+/// whole sphere is inside.
+#[derive(Debug, Component, Clone)]
+pub struct Bounds {
+    pub shape: BoundsShape,
+    pub space: BoundsSpace,
+    /// Box uses x, y and z, Sphere only uses x,
+    /// Cylinder uses x around and y along its axis.
+    pub modes: BoundsModes,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BoundsShape {
+    /// Use infinity to leave a side open, e.g. min: Vec3::new(f32::NEG_INFINITY, 0., f32::NEG_INFINITY)
+    Box {
+        min: Vec3,
+        max: Vec3,
+    },
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    /// Along the y axis.
+    Cylinder {
+        center: Vec3,
+        radius: f32,
+        half_height: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BoundsSpace {
+    /// Moves and rotates with the emitter.
+    Local,
+    World,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BoundsModes {
+    pub x: BoundsMode,
+    pub y: BoundsMode,
+    pub z: BoundsMode,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BoundsMode {
+    Kill,
+    Bounce {
+        /// number between 0 and 1, (percentage of bounciness).
+        restitution: f32,
+    },
+    /// Moves the particle to the opposite side, open sides don't wrap.
+    Wrap,
+    /// Keeps the particle on the edge and stops its movement across it.
+    Clamp,
+}
+
+impl Bounds {
+    /// The same mode for every axis.
+    pub fn new(shape: BoundsShape, space: BoundsSpace, mode: BoundsMode) -> Self {
+        Self {
+            shape,
+            space,
+            modes: BoundsModes {
+                x: mode,
+                y: mode,
+                z: mode,
+            },
+        }
+    }
+
+    /// Moves a particle back inside, returns false when it should be killed.
+    pub fn apply(&self, position: &mut Vec3, velocity: &mut Vec3, radius: f32) -> bool {
+        match self.shape {
+            BoundsShape::Box { min, max } => {
+                let modes = [self.modes.x, self.modes.y, self.modes.z];
+
+                for (axis, mode) in modes.iter().enumerate() {
+                    if !apply_interval(
+                        *mode,
+                        &mut position[axis],
+                        &mut velocity[axis],
+                        min[axis] + radius,
+                        max[axis] - radius,
+                    ) {
+                        return false;
+                    }
+                }
+
+                true
+            }
+            BoundsShape::Sphere {
+                center,
+                radius: sphere_radius,
+            } => {
+                let mut offset = *position - center;
+                let alive =
+                    apply_radial(self.modes.x, &mut offset, velocity, sphere_radius - radius);
+                *position = center + offset;
+                alive
+            }
+            BoundsShape::Cylinder {
+                center,
+                radius: cylinder_radius,
+                half_height,
+            } => {
+                let offset = *position - center;
+                let mut radial = Vec3::new(offset.x, 0., offset.z);
+
+                if !apply_radial(
+                    self.modes.x,
+                    &mut radial,
+                    velocity,
+                    cylinder_radius - radius,
+                ) {
+                    return false;
+                }
+
+                position.x = center.x + radial.x;
+                position.z = center.z + radial.z;
+
+                apply_interval(
+                    self.modes.y,
+                    &mut position.y,
+                    &mut velocity.y,
+                    center.y - half_height + radius,
+                    center.y + half_height - radius,
+                )
+            }
+        }
+    }
+}
+
+/// Keeps a coordinate between low and high, returns false when it should be killed.
+fn apply_interval(
+    mode: BoundsMode,
+    position: &mut f32,
+    velocity: &mut f32,
+    low: f32,
+    high: f32,
+) -> bool {
+    // Particles larger than the bounds stay in the middle.
+    let (low, high) = if high < low {
+        let middle = (low + high) / 2.;
+        (middle, middle)
+    } else {
+        (low, high)
+    };
+
+    if low <= *position && *position <= high {
+        return true;
+    }
+
+    let below = *position < low;
+    let edge = if below { low } else { high };
+
+    match mode {
+        BoundsMode::Kill => return false,
+        BoundsMode::Bounce { restitution } => {
+            // Mirrors the part that went through the edge.
+            *position = (2. * edge - *position).clamp(low, high);
+            *velocity = if below {
+                velocity.abs()
+            } else {
+                -velocity.abs()
+            } * restitution;
+        }
+        BoundsMode::Wrap => {
+            let size = high - low;
+
+            if size.is_finite() && 0. < size {
+                *position = low + (*position - low).rem_euclid(size);
+            } else {
+                *position = edge;
+            }
+        }
+        BoundsMode::Clamp => {
+            *position = edge;
+            *velocity = 0.;
+        }
+    }
+
+    true
+}
+
+/// Keeps an offset from the center within the limit, returns false when it should be killed.
+fn apply_radial(mode: BoundsMode, offset: &mut Vec3, velocity: &mut Vec3, limit: f32) -> bool {
+    let limit = limit.max(0.);
+    let distance = offset.length();
+
+    if distance <= limit {
+        return true;
+    }
+
+    let normal = *offset / distance;
+    let outward_speed = velocity.dot(normal).max(0.);
+
+    match mode {
+        BoundsMode::Kill => return false,
+        BoundsMode::Bounce { restitution } => {
+            *offset = normal * limit;
+            *velocity -= normal * outward_speed * (1. + restitution);
+        }
+        BoundsMode::Wrap => {
+            *offset = -normal * limit;
+        }
+        BoundsMode::Clamp => {
+            *offset = normal * limit;
+            *velocity -= normal * outward_speed;
+        }
+    }
+
+    true
+}
+
+pub fn apply_bounds_system(
+    mut particles_query: Query<(Entity, &Parent, &mut Transform, &mut Velocity), With<Particle>>,
+    emitter_query: Query<(&Bounds, &GlobalTransform), With<Emitter>>,
+    mut commands: Commands,
+) {
+    for (entity, parent, mut transform, mut velocity) in particles_query.iter_mut() {
+        let (bounds, emitter_transform) = match emitter_query.get(parent.0) {
+            Ok(emitter) => emitter,
+            Err(_) => continue,
+        };
+
+        // Particle meshes have a radius of 1, so the scale is the radius.
+        let radius = transform.scale.max_element();
+
+        let alive = match bounds.space {
+            BoundsSpace::Local => {
+                let mut position = transform.translation;
+                let mut new_velocity = velocity.to_vec3();
+                let alive = bounds.apply(&mut position, &mut new_velocity, radius);
+
+                transform.translation = position;
+                velocity.set_vec3(new_velocity);
+                alive
+            }
+            BoundsSpace::World => {
+                // Particles are children of the emitter, so their state is converted to world space.
+                let inverse_rotation = emitter_transform.rotation.inverse();
+                let mut position = emitter_transform.mul_vec3(transform.translation);
+                let mut new_velocity = velocity_to_world(emitter_transform, velocity.to_vec3());
+                let alive = bounds.apply(
+                    &mut position,
+                    &mut new_velocity,
+                    radius * emitter_transform.scale.max_element(),
+                );
+
+                transform.translation = inverse_rotation
+                    * (position - emitter_transform.translation)
+                    / emitter_transform.scale;
+                velocity.set_vec3(velocity_to_emitter(emitter_transform, new_velocity));
+                alive
+            }
+        };
+
+        if !alive {
+            commands.entity(entity).insert(Dead);
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use super::emitter_animation::EmitterAnimate;
 use super::emitter_animation::EmitterData;
+use crate::math::distribution::Distribution;
 
 pub struct EmitSpeedAnimation {
     pub from_ms: u32,
     pub until_ms: u32,
-    pub from_speed: f32,
-    pub to_speed: f32,
+    pub from_speed: Distribution<f32>,
+    pub to_speed: Distribution<f32>,
 }
 
 impl EmitterAnimate for EmitSpeedAnimation {
@@ -19,7 +20,6 @@ impl EmitterAnimate for EmitSpeedAnimation {
 
         // calculate percent from 0..1
         let fraction = delta_current as f32 / delta_max as f32;
-        data.particle_attributes.speed =
-            self.from_speed + fraction * (self.to_speed - self.from_speed);
+        data.particle_attributes.speed = self.from_speed.lerp(&self.to_speed, fraction);
     }
 }
@@ -2,17 +2,31 @@ use crate::angles::Angles;
 use crate::animations::animation::AnimationData;
 use crate::animations::animation_handler::AnimationHandler;
 use crate::animations::animation_handler::AnimationOptions;
+use crate::collisions::collider::{ColliderResponse, Stuck};
+use crate::collisions::particle_collision::{Collidable, CollisionOptions, ParticleCollisions};
+use crate::collisions::sleep::{Asleep, RestTimer, SleepOptions};
+use crate::collisions::substeps::{Substepped, Substeps};
+use crate::emitters::boids::Boids;
 use crate::emitters::emitter_animation_handler::EmitterAnimationHandler;
+use crate::fluids::sph::{Fluid, FluidParticle};
 use crate::forces::force::ForceData;
-use crate::forces::force_handler::ForceHandler;
+use crate::forces::force_field::ForceFieldLayers;
+use crate::forces::force_handler::{ForceHandler, ForceTime};
+use crate::math::distribution::Distribution;
+use crate::math::vector_grid::VectorGrid;
 //use crate::trails::trail_animation::TrailData;
 //use crate::trails::trail_handler::TrailHandler;
 use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy::utils::HashMap;
 use rand::prelude::ThreadRng;
 use rand::{thread_rng, Rng};
 use std::time::Duration;
 
+use super::bounds::{apply_bounds_system, Bounds};
 use super::emitter_animation::EmitterData;
+use super::target_shape::{assign_targets_system, TargetPosition, TargetShape};
+use super::temperature::{cool_particles_system, ParticleTemperature, Temperature, ThermalOptions};
 
 #[derive(Debug)]
 pub struct EmitterSize {
@@ -25,6 +39,9 @@ pub struct EmitterOptions {
     pub emitter_size: EmitterSize,
     pub emitter_duration: Duration,
     pub emitter_velocity: Velocity,
+    /// Fraction of the emitter world velocity particles start with, per axis.
+    /// Use Vec3::splat for a single factor, e.g. Vec3::splat(1.)
+    pub inherit_velocity: Vec3,
     pub angle_degrees: Angles,
     /// Initial spread factor x,y / z
     pub diffusion_degrees: Angles,
@@ -32,37 +49,73 @@ pub struct EmitterOptions {
     pub particle_color: Color,
     //pub particle_texture: Option<Texture2D>,
     pub particles_per_emission: u32,
+    /// 0 disables time based emission.
     pub delay_between_emission_ms: u32,
-    pub particle_lifetime: Duration,
-    pub particle_radius: f32,
-    pub particle_mass: f32,
+    /// Particles spawned per world unit the emitter travels, 0 disables distance based emission.
+    pub particles_per_distance: f32,
+    pub particle_lifetime: Distribution<Duration>,
+    pub particle_radius: Distribution<f32>,
+    pub particle_mass: Distribution<f32>,
 
     /// Newton force
-    pub particle_speed: f32,
+    pub particle_speed: Distribution<f32>,
     /// number between 0 and 1, e.g. 0.001
-    pub particle_friction_coefficient: f32,
+    pub particle_friction_coefficient: Distribution<f32>,
+    pub particle_rotation: Option<ParticleRotation>,
+    pub particle_temperature: Option<ParticleTemperature>,
+    /// Particles of this emitter collide with each other.
+    pub particle_collisions: Option<ParticleCollisions>,
+    /// How particles react to Colliders, None passes through them.
+    pub collider_response: Option<ColliderResponse>,
+    /// Moves particles in smaller steps, so fast particles don't tunnel through Colliders.
+    pub substeps: Option<Substeps>,
+    /// Resting particles fall asleep, e.g. for piles of debris.
+    pub sleep: Option<SleepOptions>,
+    /// Particles flock together, see Boids.
+    pub boids: Option<Boids>,
+    /// Particles move as a fluid, see Fluid.
+    pub fluid: Option<Fluid>,
+    /// What happens to particles that reach the edge, see BoundsMode.
     pub bounds: Option<Bounds>,
+    /// Targets for the SpringForce.
+    pub target_shape: Option<TargetShape>,
     pub particle_animation_options: Option<AnimationOptions>,
     pub emitter_animation_handler: Option<EmitterAnimationHandler>,
     pub force_handler: Option<ForceHandler>,
+    /// Layer mask of the force fields that affect the particles, 0 opts out.
+    pub force_field_layers: u32,
     //pub trail_handler: Option<TrailHandler>,
 }
 
+/// Initial orientation and spin of particles, values in degrees.
+#[derive(Debug)]
+pub struct ParticleRotation {
+    /// Initial rotation around x, y and z, randomized between -value..value.
+    pub rotation_degrees: Vec3,
+    /// Degrees per second around x, y and z.
+    pub angular_velocity_degrees: Vec3,
+    /// Randomized between -value..value and added to angular_velocity_degrees.
+    pub angular_velocity_spread_degrees: Vec3,
+    /// number between 0 and 1, e.g. 0.01
+    pub angular_drag_coefficient: f32,
+    /// Points the particle y axis in the direction it is moving, ignores angular velocity.
+    pub align_to_velocity: bool,
+}
+
 #[derive(Debug, Component)]
-pub struct Bounds {
-    pub start_x: Option<f32>,
-    pub start_y: Option<f32>,
-    pub start_z: Option<f32>,
-    pub end_x: Option<f32>,
-    pub end_y: Option<f32>,
-    pub end_z: Option<f32>,
+pub struct RotationOptions {
+    pub rotation_radians: Vec3,
+    pub angular_velocity_radians: Vec3,
+    pub angular_velocity_spread_radians: Vec3,
+    pub angular_drag_coefficient: f32,
+    pub align_to_velocity: bool,
 }
 
 #[derive(Debug, Component)]
 pub struct Emitter;
 
 #[derive(Debug, Component)]
-struct LifeCycle {
+pub struct LifeCycle {
     spawned_at: u128,
     duration_ms: u128,
     iteration: i32,
@@ -72,6 +125,15 @@ impl LifeCycle {
     pub fn elapsed_ms(&self, total_elapsed_ms: u128) -> u128 {
         total_elapsed_ms - self.spawned_at
     }
+
+    pub fn duration_ms(&self) -> u128 {
+        self.duration_ms
+    }
+
+    /// Moves the start forward, so the life cycle ends later.
+    pub fn delay(&mut self, ms: u128) {
+        self.spawned_at += ms;
+    }
 }
 
 #[derive(Debug, Component)]
@@ -80,8 +142,36 @@ pub struct EmitOptions {
     pub diffusion_radians: Angles,
     pub particles_per_emission: u32,
     pub delay_between_emission_ms: u32,
+    pub particles_per_distance: f32,
     pub emission_distortion: f32,
     pub emitter_size: EmitterSize,
+    pub inherit_velocity: Vec3,
+}
+
+/// World velocity of the emitter, derived from the GlobalTransform delta, so movement of a parent
+/// entity is included.
+#[derive(Debug, Component, Default)]
+pub struct EmitterMotion {
+    pub last_translation: Option<Vec3>,
+    /// World translation since the previous frame.
+    pub travelled: Vec3,
+    pub velocity: Vec3,
+    /// Particles that are owed by distance based emission, between 0 and 1.
+    pub distance_remainder: f32,
+    /// GlobalTransform the particles were placed relative to, see
+    /// keep_particles_in_world_space_system.
+    particle_space: Option<GlobalTransform>,
+}
+
+/// Particles are children of their emitter, so their Transform and Velocity are in emitter space.
+/// Converts a velocity of emitter space to world space.
+pub fn velocity_to_world(emitter_transform: &GlobalTransform, velocity: Vec3) -> Vec3 {
+    emitter_transform.rotation * (velocity * emitter_transform.scale)
+}
+
+/// Converts a world space velocity to the space of the emitter.
+pub fn velocity_to_emitter(emitter_transform: &GlobalTransform, velocity: Vec3) -> Vec3 {
+    emitter_transform.rotation.inverse() * velocity / emitter_transform.scale
 }
 
 impl EmitOptions {
@@ -90,21 +180,27 @@ impl EmitOptions {
     }
 }
 
-/// Which values particles are deployed with.
+/// Which values particles are deployed with, every particle draws its own values.
 #[derive(Debug, Component)]
 pub struct EmitterParticleAttributes {
-    pub duration_ms: u128,
-    pub radius: f32,
-    pub mass: f32,
-    pub speed: f32,
-    pub friction_coefficient: f32,
+    pub duration_ms: Distribution<u128>,
+    pub radius: Distribution<f32>,
+    pub mass: Distribution<f32>,
+    pub speed: Distribution<f32>,
+    pub friction_coefficient: Distribution<f32>,
     pub color: Color,
 }
 
 #[derive(Debug, Component)]
 pub struct Particle;
 
-#[derive(Component, Debug, Default)]
+/// Particle that is killed, e.g. by its lifetime, bounds or a collider. Dead particles are
+/// despawned by a single system after the update, so two kills in a frame don't despawn twice.
+/// Killing a dead particle again is harmless.
+#[derive(Debug, Component)]
+pub struct Dead;
+
+#[derive(Component, Debug, Default, Clone, Copy)]
 pub struct Velocity {
     pub vx: f32,
     pub vy: f32,
@@ -119,67 +215,170 @@ impl Velocity {
     pub fn zero() -> Self {
         Default::default()
     }
+
+    pub fn from_vec3(vec: Vec3) -> Self {
+        Self::new(vec.x, vec.y, vec.z)
+    }
+
+    pub fn to_vec3(&self) -> Vec3 {
+        Vec3::new(self.vx, self.vy, self.vz)
+    }
+
+    pub fn set_vec3(&mut self, vec: Vec3) {
+        self.vx = vec.x;
+        self.vy = vec.y;
+        self.vz = vec.z;
+    }
+}
+
+/// Radians per second around x, y and z.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct AngularVelocity {
+    pub wx: f32,
+    pub wy: f32,
+    pub wz: f32,
+}
+
+impl AngularVelocity {
+    pub fn new(wx: f32, wy: f32, wz: f32) -> Self {
+        Self { wx, wy, wz }
+    }
+
+    pub fn zero() -> Self {
+        Default::default()
+    }
+
+    pub fn from_vec3(vec: Vec3) -> Self {
+        Self::new(vec.x, vec.y, vec.z)
+    }
+
+    pub fn to_vec3(&self) -> Vec3 {
+        Vec3::new(self.wx, self.wy, self.wz)
+    }
+
+    pub fn set_vec3(&mut self, vec: Vec3) {
+        self.wx = vec.x;
+        self.wy = vec.y;
+        self.wz = vec.z;
+    }
+
+    /// Adds spin to a (solid sphere) particle which changed velocity by a tangential impact.
+    /// contact_offset is the vector from the particle center to the contact point.
+    pub fn add_tangential_impact(&mut self, contact_offset: Vec3, delta_velocity: Vec3) {
+        let distance_pow = contact_offset.length_squared();
+
+        if distance_pow == 0. {
+            return;
+        }
+
+        // Moment of inertia of a solid sphere is 2/5 * m * r^2, the mass cancels out.
+        let delta = contact_offset.cross(delta_velocity) * 5. / (2. * distance_pow);
+        self.set_vec3(self.to_vec3() + delta);
+    }
 }
 
 #[derive(Debug, Component)]
 pub struct ParticleAttributes {
-    radius: f32,
-    mass: f32,
-    friction_coefficient: f32,
+    pub radius: f32,
+    pub mass: f32,
+    pub friction_coefficient: f32,
 }
 
 #[derive(Component)]
 pub struct Materials {
     particle_mesh: Handle<Mesh>,
     particle_material: Handle<StandardMaterial>,
+    /// Particles get a copy of the material, so color animations can change it per particle.
+    own_particle_materials: bool,
 }
 
 const EMIT_RADIANS: f32 = 90_f32 * (std::f32::consts::PI / 180_f32); // 0 deg will be emitting above
 
+const MS_PER_SEC: f32 = 1000.;
+
+// Sampled distributions can go below zero.
+const MIN_RADIUS: f32 = 0.001;
+const MIN_MASS: f32 = 0.001;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub enum EmitterSystem {
+    /// Updates the EmitterMotion of the frame, read by distance based emission.
+    TrackMotion,
+    /// Moves particles by their velocity, systems that steer particles go before it.
+    Move,
+}
+
 pub struct EmitterPlugin;
 
 impl Plugin for EmitterPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(transform_particle_system)
-            .add_system(spawn_particles_system)
+        app.add_system(transform_particle_system.label(EmitterSystem::Move))
+            .add_system(rotate_particle_system)
+            .add_system(track_emitter_motion_system.label(EmitterSystem::TrackMotion))
+            .add_system(spawn_particles_system.after(EmitterSystem::TrackMotion))
             .add_system(apply_forces_system)
             .add_system(apply_animations_system)
             .add_system(remove_particles_system)
-            .add_system(animate_emitter_system);
+            .add_system(apply_bounds_system)
+            .add_system(animate_emitter_system)
+            .add_system(assign_targets_system)
+            .add_system(cool_particles_system)
+            .add_system_to_stage(CoreStage::PostUpdate, despawn_dead_particles_system)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                keep_particles_in_world_space_system.after(TransformSystem::TransformPropagate),
+            );
     }
 }
 
 fn apply_forces_system(
     mut particles_query: Query<
-        (&Parent, &mut Velocity, &Transform, &ParticleAttributes),
-        With<Particle>,
+        (
+            &Parent,
+            &mut Velocity,
+            &Transform,
+            &ParticleAttributes,
+            &LifeCycle,
+            Option<&TargetPosition>,
+            Option<&Temperature>,
+        ),
+        (With<Particle>, Without<Asleep>),
     >,
     emitter_query: Query<(&ForceHandler, &LifeCycle), With<Emitter>>,
+    vector_grids: Option<Res<Assets<VectorGrid>>>,
     time: Res<Time>,
 ) {
     let total_elapsed_ms = time.time_since_startup().as_millis();
     let delta_seconds = time.delta_seconds();
 
-    for (parent, mut velocity, transform, attributes) in particles_query.iter_mut() {
-        let (force_handler, life_cycle) = emitter_query.get(parent.0).unwrap();
-        let elapsed_ms = life_cycle.elapsed_ms(total_elapsed_ms);
+    for (parent, mut velocity, transform, attributes, particle_life_cycle, target, temperature) in
+        particles_query.iter_mut()
+    {
+        let (force_handler, life_cycle) = match emitter_query.get(parent.0) {
+            Ok(emitter) => emitter,
+            Err(_) => continue,
+        };
 
-        let scale = &transform.scale;
-        let radius = Vec3::new(
-            scale.x * attributes.radius,
-            scale.y * attributes.radius,
-            scale.z * attributes.radius,
-        );
+        let time = ForceTime {
+            emitter_elapsed_ms: life_cycle.elapsed_ms(total_elapsed_ms),
+            particle_age_ms: particle_life_cycle.elapsed_ms(total_elapsed_ms),
+            particle_lifetime_ms: particle_life_cycle.duration_ms,
+        };
 
+        // Particle meshes have a radius of 1, so the scale is the radius.
         let mut data = ForceData {
             position: &transform.translation,
             velocity: &mut velocity,
-            radius,
+            radius: transform.scale,
             mass: attributes.mass,
             delta_seconds,
+            total_elapsed_ms,
+            target: target.map(|target| target.0),
+            temperature: temperature.map(|temperature| temperature.0),
+            vector_grids: vector_grids.as_deref(),
         };
 
-        force_handler.apply(&mut data, elapsed_ms);
+        force_handler.apply(&mut data, &time);
     }
 }
 
@@ -188,91 +387,154 @@ fn apply_animations_system(
         (
             &Parent,
             &mut Velocity,
+            &mut AngularVelocity,
             &Handle<StandardMaterial>,
             &mut Transform,
             &LifeCycle,
+            Option<&Temperature>,
         ),
         With<Particle>,
     >,
-    mut emitter_query: Query<&mut AnimationHandler, With<Emitter>>,
+    mut emitter_query: Query<
+        (
+            &mut AnimationHandler,
+            &EmitterParticleAttributes,
+            &Materials,
+        ),
+        With<Emitter>,
+    >,
+    mut materials: ResMut<Assets<StandardMaterial>>,
     time: Res<Time>,
 ) {
     let total_elapsed_ms = time.time_since_startup().as_millis();
 
-    for (parent, mut velocity, handle, mut transform, life_cycle) in particles_query.iter_mut() {
-        let mut animation_handler = emitter_query.get_mut(parent.0).unwrap();
-        //let material = &mut materials.get_mut(handle).unwrap();
+    for (
+        parent,
+        mut velocity,
+        mut angular_velocity,
+        handle,
+        mut transform,
+        life_cycle,
+        temperature,
+    ) in particles_query.iter_mut()
+    {
+        // Emitters without particle animations have no handler.
+        let (mut animation_handler, particle_attributes, emitter_materials) =
+            match emitter_query.get_mut(parent.0) {
+                Ok(emitter) => emitter,
+                Err(_) => continue,
+            };
 
-        let mut color = Color::rgba(0., 0., 0., 1.);
+        // Color animations start from the particle color every frame.
+        let mut color = particle_attributes.color;
         let mut data = AnimationData {
             color: &mut color,
             scale: &mut transform.scale,
             velocity: &mut velocity,
+            angular_velocity: &mut angular_velocity,
+            temperature: temperature.map(|temperature| temperature.0),
         };
 
         animation_handler.apply(&mut data, life_cycle.elapsed_ms(total_elapsed_ms));
+
+        if emitter_materials.own_particle_materials {
+            if let Some(material) = materials.get_mut(handle) {
+                material.base_color = color;
+            }
+        }
     }
 }
 
 fn remove_particles_system(
-    particles_query: Query<
-        (Entity, &Parent, &Transform, &ParticleAttributes, &LifeCycle),
-        With<Particle>,
-    >,
-    emitter_query: Query<Option<&Bounds>>,
+    particles_query: Query<(Entity, &LifeCycle), With<Particle>>,
     mut commands: Commands,
     time: Res<Time>,
 ) {
     let total_elapsed_ms = time.time_since_startup().as_millis();
 
-    for (entity, parent, transform, attributes, life_cycle) in particles_query.iter() {
-        let bounds = emitter_query.get(parent.0).unwrap();
-
+    for (entity, life_cycle) in particles_query.iter() {
         if life_cycle.duration_ms < life_cycle.elapsed_ms(total_elapsed_ms) {
-            commands.entity(parent.0).remove_children(&[entity]);
-            commands.entity(entity).despawn();
-            continue;
+            commands.entity(entity).insert(Dead);
         }
+    }
+}
 
-        let translation = &transform.translation;
-        let diameter = attributes.radius * 2.;
-
-        if let Some(bounds) = bounds {
-            let below_x = bounds
-                .start_x
-                .map_or(false, |start_x| translation.x < start_x);
-
-            let below_y = bounds
-                .start_y
-                .map_or(false, |start_y| translation.y < start_y);
+fn despawn_dead_particles_system(
+    particles_query: Query<(Entity, &Parent), With<Dead>>,
+    mut commands: Commands,
+) {
+    for (entity, parent) in particles_query.iter() {
+        commands.entity(parent.0).remove_children(&[entity]);
+        commands.entity(entity).despawn();
+    }
+}
 
-            let below_z = bounds
-                .start_z
-                .map_or(false, |start_z| translation.z < start_z);
+/// Particles move in world space, so a moving emitter leaves them behind. The transform
+/// propagation moves children along with their parent, so particles are placed back where they
+/// were relative to the emitter transform they were simulated in.
+fn keep_particles_in_world_space_system(
+    mut emitter_query: Query<(Entity, &GlobalTransform, &mut EmitterMotion), With<Emitter>>,
+    mut particles_query: Query<
+        (
+            &Parent,
+            &mut Transform,
+            &mut GlobalTransform,
+            &mut Velocity,
+            &mut AngularVelocity,
+        ),
+        (With<Particle>, Without<Emitter>),
+    >,
+) {
+    // Previous and current transform of the emitters that moved.
+    let mut moved: HashMap<Entity, (GlobalTransform, GlobalTransform)> = HashMap::default();
 
-            let above_x = bounds
-                .end_x
-                .map_or(false, |end_x| end_x < translation.x + diameter);
+    for (entity, emitter_transform, mut motion) in emitter_query.iter_mut() {
+        if let Some(previous) = motion.particle_space.replace(*emitter_transform) {
+            if previous != *emitter_transform {
+                moved.insert(entity, (previous, *emitter_transform));
+            }
+        }
+    }
 
-            let above_y = bounds
-                .end_y
-                .map_or(false, |end_y| end_y < translation.y + diameter);
+    if moved.is_empty() {
+        return;
+    }
 
-            let above_z = bounds
-                .end_z
-                .map_or(false, |end_z| end_z < translation.z + diameter);
+    // Also particles spawned this frame, they aren't in the Children of the emitter yet.
+    for (parent, mut transform, mut global_transform, mut velocity, mut angular_velocity) in
+        particles_query.iter_mut()
+    {
+        let (previous, current) = match moved.get(&parent.0) {
+            Some(transforms) => transforms,
+            None => continue,
+        };
 
-            if below_x || below_y || below_z || above_x || above_y || above_z {
-                commands.entity(parent.0).remove_children(&[entity]);
-                commands.entity(entity).despawn();
-                continue;
-            }
-        }
+        let inverse_rotation = current.rotation.inverse();
+        let world = previous.mul_transform(*transform);
+        transform.translation =
+            inverse_rotation * (world.translation - current.translation) / current.scale;
+        transform.rotation = inverse_rotation * world.rotation;
+        transform.scale = world.scale / current.scale;
+        *global_transform = world;
+
+        let world_velocity = velocity_to_world(previous, velocity.to_vec3());
+        velocity.set_vec3(velocity_to_emitter(current, world_velocity));
+        let rotated = inverse_rotation * previous.rotation * angular_velocity.to_vec3();
+        angular_velocity.set_vec3(rotated);
     }
 }
 
 fn transform_particle_system(
-    mut query: Query<(&mut Velocity, &mut Transform, &ParticleAttributes), With<Particle>>,
+    mut query: Query<
+        (&mut Velocity, &mut Transform, &ParticleAttributes),
+        (
+            With<Particle>,
+            Without<Stuck>,
+            Without<Substepped>,
+            Without<Asleep>,
+            Without<FluidParticle>,
+        ),
+    >,
     time: Res<Time>,
 ) {
     let delta = time.delta_seconds();
@@ -293,29 +555,110 @@ fn transform_particle_system(
     }
 }
 
+fn rotate_particle_system(
+    mut query: Query<
+        (&Parent, &Velocity, &mut AngularVelocity, &mut Transform),
+        (With<Particle>, Without<Asleep>),
+    >,
+    emitter_query: Query<Option<&RotationOptions>, With<Emitter>>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+
+    for (parent, velocity, mut angular_velocity, mut transform) in query.iter_mut() {
+        let rotation_options = emitter_query.get(parent.0).unwrap();
+
+        if let Some(options) = rotation_options {
+            let drag_multiplier = 1. - options.angular_drag_coefficient;
+            angular_velocity.wx *= drag_multiplier;
+            angular_velocity.wy *= drag_multiplier;
+            angular_velocity.wz *= drag_multiplier;
+
+            if options.align_to_velocity {
+                let direction = velocity.to_vec3();
+
+                if direction != Vec3::ZERO {
+                    transform.rotation = Quat::from_rotation_arc(Vec3::Y, direction.normalize());
+                }
+
+                continue;
+            }
+        }
+
+        let rotation = Quat::from_scaled_axis(angular_velocity.to_vec3() * delta);
+        transform.rotation = (rotation * transform.rotation).normalize();
+    }
+}
+
+fn track_emitter_motion_system(
+    mut query: Query<(&GlobalTransform, &mut EmitterMotion), With<Emitter>>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+
+    for (global_transform, mut motion) in query.iter_mut() {
+        let translation = global_transform.translation;
+
+        if let Some(last_translation) = motion.last_translation {
+            motion.travelled = translation - last_translation;
+
+            if 0. < delta_seconds {
+                motion.velocity = motion.travelled / delta_seconds;
+            }
+        }
+
+        motion.last_translation = Some(translation);
+    }
+}
+
 fn spawn_particles_system(
     mut query: Query<
         (
             &mut LifeCycle,
+            &mut EmitterMotion,
             &EmitOptions,
             &EmitterParticleAttributes,
             &Materials,
+            &GlobalTransform,
+            Option<&RotationOptions>,
+            Option<&ThermalOptions>,
+            Option<&CollisionOptions>,
+            Option<&Substeps>,
+            Option<&SleepOptions>,
+            Option<&Fluid>,
+            Option<&mut TargetShape>,
             Option<&Children>,
             Entity,
         ),
         With<Emitter>,
     >,
+    mut materials: ResMut<Assets<StandardMaterial>>,
     mut commands: Commands,
     time: Res<Time>,
 ) {
     let total_elapsed_ms = time.time_since_startup().as_millis();
+    let delta_seconds = time.delta_seconds();
 
-    for (mut life_cycle, emit_options, particle_attributes, meshes, children, entity) in
-        query.iter_mut()
+    for (
+        mut life_cycle,
+        mut motion,
+        emit_options,
+        particle_attributes,
+        meshes,
+        global_transform,
+        rotation_options,
+        thermal_options,
+        collision_options,
+        substeps,
+        sleep_options,
+        fluid,
+        mut target_shape,
+        children,
+        entity,
+    ) in query.iter_mut()
     {
         let elapsed_ms = life_cycle.elapsed_ms(total_elapsed_ms);
         let out_of_time = life_cycle.duration_ms < elapsed_ms;
-        let new_iteration = elapsed_ms as i32 / emit_options.delay_between_emission_ms as i32;
 
         if out_of_time {
             if let Some(children) = children {
@@ -323,68 +666,218 @@ fn spawn_particles_system(
                     commands.entity(entity).despawn_recursive();
                 }
             }
-            return;
-        } else if new_iteration == life_cycle.iteration {
-            return;
+            continue;
         }
 
-        life_cycle.iteration = new_iteration;
-
         let mut rng = thread_rng();
+        let inverse_rotation = global_transform.rotation.inverse();
+
+        let mut spawner = ParticleSpawner {
+            emitter: entity,
+            emit_options,
+            particle_attributes,
+            meshes,
+            materials: &mut materials,
+            rotation_options,
+            thermal_options,
+            collision_options,
+            substepped: substeps.is_some(),
+            sleeps: sleep_options.is_some(),
+            fluid: fluid.is_some(),
+            target_shape: target_shape.as_deref_mut(),
+            inherited_velocity: velocity_to_emitter(
+                global_transform,
+                motion.velocity * emit_options.inherit_velocity,
+            ),
+            emitter_elapsed_ms: elapsed_ms,
+            total_elapsed_ms,
+        };
 
-        for _ in 0..emit_options.particles_per_emission {
-            let emitter_length = gen_abs_range(&mut rng, emit_options.emitter_size.length);
-            let emitter_depth = gen_abs_range(&mut rng, emit_options.emitter_size.depth);
-            let distortion = gen_dyn_range(&mut rng, emit_options.emission_distortion);
-
-            let Angles { elevation, bearing } = emit_options.angle_radians;
-            // Used to emit perpendicular of emitter.
-            let perpendicular = elevation.cos() * -1.;
-            let x = distortion + emitter_length * perpendicular * bearing.cos();
-            let y = distortion + emitter_length * elevation.sin() * bearing.cos();
-            let z = (distortion + emitter_depth) + emitter_length * bearing.sin();
-
-            let diffusion_elevation_delta =
-                gen_dyn_range(&mut rng, emit_options.diffusion_radians.elevation);
-            let bearing_radians = gen_dyn_range(&mut rng, emit_options.diffusion_radians.bearing);
-            let elevation_radians =
-                emit_options.angle_emission_radians() + diffusion_elevation_delta;
-
-            // Used to emit perpendicular of emitter.
-            let perpendicular = elevation_radians.cos() * -1.;
-            let vx = particle_attributes.speed * perpendicular * bearing_radians.cos();
-            let vy = particle_attributes.speed * elevation_radians.sin() * bearing_radians.cos();
-            let vz = particle_attributes.speed * bearing_radians.sin();
-
-            let pbr_bundle = PbrBundle {
-                material: meshes.particle_material.clone(),
-                mesh: meshes.particle_mesh.clone(),
-                transform: Transform {
-                    translation: Vec3::new(x, y, z),
-                    ..Default::default()
-                },
-                ..Default::default()
-            };
+        if 0 < emit_options.delay_between_emission_ms {
+            let new_iteration = elapsed_ms as i32 / emit_options.delay_between_emission_ms as i32;
 
-            let speed = Velocity { vx, vy, vz };
-            let life_cycle = LifeCycle {
-                spawned_at: total_elapsed_ms,
-                duration_ms: particle_attributes.duration_ms,
-                iteration: -1,
-            };
+            if new_iteration != life_cycle.iteration {
+                life_cycle.iteration = new_iteration;
 
-            let attributes = ParticleAttributes {
-                friction_coefficient: particle_attributes.friction_coefficient,
-                radius: particle_attributes.radius,
-                mass: particle_attributes.mass,
-            };
+                for _ in 0..emit_options.particles_per_emission {
+                    spawner.spawn(&mut commands, &mut rng, Vec3::ZERO, 0.);
+                }
+            }
+        }
 
-            commands
-                .spawn()
-                .insert_bundle(pbr_bundle)
-                .insert(Parent(entity))
-                .insert_bundle((speed, life_cycle, attributes, Particle))
-                .id();
+        if 0. < emit_options.particles_per_distance {
+            let travelled = motion.travelled;
+            let distance = travelled.length();
+            let owed = motion.distance_remainder + distance * emit_options.particles_per_distance;
+            let count = owed.floor() as u32;
+
+            for i in 1..=count {
+                // Fraction of the path between the previous and current emitter position.
+                let path_fraction = (i as f32 - motion.distance_remainder)
+                    / emit_options.particles_per_distance
+                    / distance;
+                // Particles stay in world space, so the batch is left behind along the path.
+                let path_offset =
+                    inverse_rotation * (travelled * (path_fraction - 1.)) / global_transform.scale;
+                let age_seconds = (1. - path_fraction) * delta_seconds;
+
+                spawner.spawn(&mut commands, &mut rng, path_offset, age_seconds);
+            }
+
+            motion.distance_remainder = owed - count as f32;
+        }
+    }
+}
+
+struct ParticleSpawner<'a> {
+    emitter: Entity,
+    emit_options: &'a EmitOptions,
+    particle_attributes: &'a EmitterParticleAttributes,
+    meshes: &'a Materials,
+    materials: &'a mut Assets<StandardMaterial>,
+    rotation_options: Option<&'a RotationOptions>,
+    thermal_options: Option<&'a ThermalOptions>,
+    collision_options: Option<&'a CollisionOptions>,
+    substepped: bool,
+    sleeps: bool,
+    fluid: bool,
+    target_shape: Option<&'a mut TargetShape>,
+    inherited_velocity: Vec3,
+    emitter_elapsed_ms: u128,
+    total_elapsed_ms: u128,
+}
+
+impl<'a> ParticleSpawner<'a> {
+    /// Particles with an age are moved ahead as if they were spawned age_seconds ago.
+    fn spawn(
+        &mut self,
+        commands: &mut Commands,
+        rng: &mut ThreadRng,
+        path_offset: Vec3,
+        age_seconds: f32,
+    ) {
+        let emit_options = self.emit_options;
+        let particle_attributes = self.particle_attributes;
+        let elapsed_ms = self.emitter_elapsed_ms;
+
+        let emitter_length = gen_abs_range(rng, emit_options.emitter_size.length);
+        let emitter_depth = gen_abs_range(rng, emit_options.emitter_size.depth);
+        let distortion = gen_dyn_range(rng, emit_options.emission_distortion);
+
+        let Angles { elevation, bearing } = emit_options.angle_radians;
+        // Used to emit perpendicular of emitter.
+        let perpendicular = elevation.cos() * -1.;
+        let x = distortion + emitter_length * perpendicular * bearing.cos();
+        let y = distortion + emitter_length * elevation.sin() * bearing.cos();
+        let z = (distortion + emitter_depth) + emitter_length * bearing.sin();
+
+        let diffusion_elevation_delta =
+            gen_dyn_range(rng, emit_options.diffusion_radians.elevation);
+        let bearing_radians = gen_dyn_range(rng, emit_options.diffusion_radians.bearing);
+        let elevation_radians = emit_options.angle_emission_radians() + diffusion_elevation_delta;
+
+        let speed = particle_attributes.speed.sample(rng, elapsed_ms);
+        let radius = particle_attributes
+            .radius
+            .sample(rng, elapsed_ms)
+            .max(MIN_RADIUS);
+        let mass = particle_attributes
+            .mass
+            .sample(rng, elapsed_ms)
+            .max(MIN_MASS);
+
+        // Used to emit perpendicular of emitter.
+        let perpendicular = elevation_radians.cos() * -1.;
+        let vx = speed * perpendicular * bearing_radians.cos();
+        let vy = speed * elevation_radians.sin() * bearing_radians.cos();
+        let vz = speed * bearing_radians.sin();
+
+        let (rotation, angular_velocity) = match self.rotation_options {
+            Some(options) => {
+                let rotation = gen_dyn_vec3(rng, options.rotation_radians);
+                let spread = gen_dyn_vec3(rng, options.angular_velocity_spread_radians);
+
+                (
+                    Quat::from_euler(EulerRot::XYZ, rotation.x, rotation.y, rotation.z),
+                    AngularVelocity::from_vec3(options.angular_velocity_radians + spread),
+                )
+            }
+            None => (Quat::IDENTITY, AngularVelocity::zero()),
+        };
+
+        let velocity = Vec3::new(vx, vy, vz) + self.inherited_velocity;
+        let translation = Vec3::new(x, y, z) + path_offset + velocity * age_seconds;
+
+        let material = match self.materials.get(&self.meshes.particle_material) {
+            Some(material) if self.meshes.own_particle_materials => {
+                let material = material.clone();
+                self.materials.add(material)
+            }
+            _ => self.meshes.particle_material.clone(),
+        };
+
+        let pbr_bundle = PbrBundle {
+            material,
+            mesh: self.meshes.particle_mesh.clone(),
+            transform: Transform {
+                translation,
+                rotation,
+                scale: Vec3::splat(radius),
+            },
+            ..Default::default()
+        };
+
+        let age_ms = (age_seconds * MS_PER_SEC) as u128;
+        let life_cycle = LifeCycle {
+            spawned_at: self.total_elapsed_ms.saturating_sub(age_ms),
+            duration_ms: particle_attributes.duration_ms.sample(rng, elapsed_ms),
+            iteration: -1,
+        };
+
+        let attributes = ParticleAttributes {
+            friction_coefficient: particle_attributes
+                .friction_coefficient
+                .sample(rng, elapsed_ms),
+            radius,
+            mass,
+        };
+
+        let mut builder = commands.spawn();
+        builder
+            .insert_bundle(pbr_bundle)
+            .insert(Parent(self.emitter))
+            .insert_bundle((
+                Velocity::from_vec3(velocity),
+                angular_velocity,
+                life_cycle,
+                attributes,
+                Particle,
+            ));
+
+        if let Some(options) = self.thermal_options {
+            builder.insert(Temperature(options.temperature.sample(rng, elapsed_ms)));
+        }
+
+        if let Some(options) = self.collision_options {
+            builder.insert(Collidable {
+                elasticity: options.elasticity.sample(rng, elapsed_ms).clamp(0., 1.),
+            });
+        }
+
+        if self.substepped {
+            builder.insert(Substepped);
+        }
+
+        if self.sleeps {
+            builder.insert(RestTimer::default());
+        }
+
+        if self.fluid {
+            builder.insert(FluidParticle);
+        }
+
+        if let Some(target_shape) = self.target_shape.as_mut() {
+            builder.insert(target_shape.next_slot());
         }
     }
 }
@@ -450,16 +943,28 @@ impl Emitter {
             particle_color,
             particles_per_emission,
             delay_between_emission_ms,
+            particles_per_distance,
             particle_lifetime,
             particle_radius,
             particle_mass,
             particle_speed,
             particle_friction_coefficient,
+            particle_rotation,
+            particle_temperature,
+            particle_collisions,
+            collider_response,
+            substeps,
+            sleep,
+            boids,
+            fluid,
             bounds,
+            target_shape,
             particle_animation_options,
             emitter_animation_handler,
             force_handler,
+            force_field_layers,
             emitter_velocity,
+            inherit_velocity,
         } = options;
 
         let emit_options = EmitOptions {
@@ -467,8 +972,10 @@ impl Emitter {
             diffusion_radians: diffusion_degrees.to_radians(),
             angle_radians: angle_degrees.to_radians(),
             delay_between_emission_ms,
+            particles_per_distance,
             emission_distortion,
             emitter_size,
+            inherit_velocity,
         };
 
         let emit_time = LifeCycle {
@@ -482,13 +989,22 @@ impl Emitter {
             color: particle_color,
             friction_coefficient: particle_friction_coefficient,
             radius: particle_radius,
-            duration_ms: particle_lifetime.as_millis(),
+            duration_ms: particle_lifetime.map(|lifetime| lifetime.as_millis()),
             mass: particle_mass,
         };
 
+        let own_particle_materials = particle_animation_options
+            .as_ref()
+            .map_or(false, |options| {
+                options
+                    .animations
+                    .iter()
+                    .any(|layer| layer.animation.channels().color)
+            });
+
         let meshes = Materials {
             particle_mesh: meshes.add(Mesh::from(shape::Icosphere {
-                radius: particle_radius,
+                radius: 1.,
                 ..Default::default()
             })),
             particle_material: materials.add(StandardMaterial {
@@ -496,10 +1012,13 @@ impl Emitter {
                 //alpha_mode: AlphaMode::Blend,
                 ..Default::default()
             }),
+            own_particle_materials,
         };
 
         let pbr_bundle = PbrBundle {
             transform: emitter_transform,
+            // Avoids a jump in the emitter motion before the transform is propagated.
+            global_transform: GlobalTransform::from(emitter_transform),
             ..Default::default()
         };
 
@@ -510,6 +1029,8 @@ impl Emitter {
             .insert(spawn_options)
             .insert(meshes)
             .insert(emitter_velocity)
+            .insert(EmitterMotion::default())
+            .insert(ForceFieldLayers(force_field_layers))
             .insert_bundle(pbr_bundle)
             .insert(Emitter);
 
@@ -517,6 +1038,50 @@ impl Emitter {
             builder.insert(bounds);
         }
 
+        if let Some(temperature) = particle_temperature {
+            builder.insert(ThermalOptions::from(temperature));
+        }
+
+        if let Some(collisions) = particle_collisions {
+            builder.insert(CollisionOptions::from(collisions));
+        }
+
+        if let Some(response) = collider_response {
+            builder.insert(response);
+        }
+
+        if let Some(substeps) = substeps {
+            builder.insert(substeps);
+        }
+
+        if let Some(sleep) = sleep {
+            builder.insert(sleep);
+        }
+
+        if let Some(boids) = boids {
+            builder.insert(boids);
+        }
+
+        if let Some(fluid) = fluid {
+            builder.insert(fluid);
+        }
+
+        if let Some(target_shape) = target_shape {
+            builder.insert(target_shape);
+        }
+
+        if let Some(rotation) = particle_rotation {
+            builder.insert(RotationOptions {
+                rotation_radians: degrees_to_radians(rotation.rotation_degrees),
+                angular_velocity_radians: degrees_to_radians(rotation.angular_velocity_degrees),
+                angular_velocity_spread_radians: degrees_to_radians(
+                    rotation.angular_velocity_spread_degrees,
+                ),
+                angular_drag_coefficient: rotation.angular_drag_coefficient,
+                align_to_velocity: rotation.align_to_velocity,
+            });
+        }
+
         if let Some(force_handler) = force_handler {
             builder.insert(force_handler);
         }
@@ -568,3 +1133,254 @@ fn gen_abs_range(rng: &mut ThreadRng, val: f32) -> f32 {
         0.
     }
 }
+
+fn gen_dyn_vec3(rng: &mut ThreadRng, val: Vec3) -> Vec3 {
+    Vec3::new(
+        gen_dyn_range(rng, val.x),
+        gen_dyn_range(rng, val.y),
+        gen_dyn_range(rng, val.z),
+    )
+}
+
+fn degrees_to_radians(degrees: Vec3) -> Vec3 {
+    Vec3::new(
+        degrees.x.to_radians(),
+        degrees.y.to_radians(),
+        degrees.z.to_radians(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animations::animation_handler::StartAnimationAt;
+    use crate::animations::blackbody_animation::BlackbodyAnimation;
+    use crate::emitters::temperature::{blackbody_color, ParticleTemperature};
+    use crate::forces::force_field::ALL_LAYERS;
+    use bevy::asset::AssetPlugin;
+    use bevy::core::CorePlugin;
+    use bevy::transform::TransformPlugin;
+
+    /// Still particles, emitted once at the start and by distance when the emitter moves.
+    fn options() -> EmitterOptions {
+        EmitterOptions {
+            emitter_transform: Transform::identity(),
+            emitter_size: EmitterSize {
+                length: 1.,
+                depth: 1.,
+            },
+            emitter_duration: Duration::from_secs(60),
+            angle_degrees: Angles::new(0., 0.),
+            diffusion_degrees: Angles::new(0., 0.),
+            emission_distortion: 0.,
+            emitter_velocity: Velocity::zero(),
+            inherit_velocity: Vec3::ONE,
+            particle_color: Color::WHITE,
+            particles_per_emission: 5,
+            delay_between_emission_ms: 60_000,
+            particles_per_distance: 0.,
+            particle_lifetime: Distribution::Constant(Duration::from_secs(60)),
+            particle_radius: Distribution::Constant(0.1),
+            particle_mass: Distribution::Constant(1.),
+            particle_speed: Distribution::Constant(0.),
+            particle_friction_coefficient: Distribution::Constant(0.),
+            particle_rotation: None,
+            particle_temperature: None,
+            particle_collisions: None,
+            collider_response: None,
+            substeps: None,
+            sleep: None,
+            boids: None,
+            fluid: None,
+            bounds: None,
+            target_shape: None,
+            particle_animation_options: None,
+            emitter_animation_handler: None,
+            force_handler: None,
+            force_field_layers: ALL_LAYERS,
+        }
+    }
+
+    fn emitter_app(options: fn() -> EmitterOptions) -> App {
+        let mut app = App::new();
+        app.add_plugin(CorePlugin)
+            .add_plugin(TransformPlugin)
+            .add_plugin(AssetPlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_plugin(EmitterPlugin)
+            .insert_resource(options)
+            .add_startup_system(setup);
+        app
+    }
+
+    fn setup(
+        mut commands: Commands,
+        meshes: ResMut<Assets<Mesh>>,
+        materials: ResMut<Assets<StandardMaterial>>,
+        options: Res<fn() -> EmitterOptions>,
+    ) {
+        Emitter::create(options(), &mut commands, meshes, materials, 0);
+    }
+
+    fn move_emitter(app: &mut App, transform: Transform) {
+        let mut emitter_query = app.world.query_filtered::<&mut Transform, With<Emitter>>();
+        for mut emitter_transform in emitter_query.iter_mut(&mut app.world) {
+            *emitter_transform = transform;
+        }
+    }
+
+    /// World positions and velocities of the particles.
+    fn particles(app: &mut App) -> HashMap<Entity, (Vec3, Vec3)> {
+        let mut emitter_query = app.world.query::<&GlobalTransform>();
+        let mut particles_query = app
+            .world
+            .query_filtered::<(Entity, &Parent, &Transform, &Velocity), With<Particle>>();
+
+        particles_query
+            .iter(&app.world)
+            .map(|(entity, parent, transform, velocity)| {
+                let emitter_transform = emitter_query.get(&app.world, parent.0).unwrap();
+                let position = emitter_transform.mul_vec3(transform.translation);
+                let velocity = velocity_to_world(emitter_transform, velocity.to_vec3());
+                (entity, (position, velocity))
+            })
+            .collect()
+    }
+
+    /// Particles at the emitter origin, only emitted by distance.
+    fn trail_options() -> EmitterOptions {
+        EmitterOptions {
+            emitter_transform: trail_transform(0.),
+            emitter_size: EmitterSize {
+                length: 0.,
+                depth: 0.,
+            },
+            inherit_velocity: Vec3::ZERO,
+            delay_between_emission_ms: 0,
+            particles_per_distance: 4.,
+            ..options()
+        }
+    }
+
+    /// Rotated and scaled, so the path has to be converted to emitter space.
+    fn trail_transform(x: f32) -> Transform {
+        Transform {
+            translation: Vec3::new(x, 0., 0.),
+            rotation: Quat::from_rotation_y(1.),
+            scale: Vec3::splat(2.),
+        }
+    }
+
+    #[test]
+    fn distance_emission_leaves_a_trail() {
+        let mut app = emitter_app(trail_options);
+        app.update();
+
+        for x in 1..=10 {
+            move_emitter(&mut app, trail_transform(x as f32));
+            app.update();
+        }
+
+        // Motion is tracked a frame later.
+        app.update();
+
+        let mut xs: Vec<f32> = particles(&mut app)
+            .values()
+            .map(|(position, _)| position.x)
+            .collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        assert_eq!(xs.len(), 40);
+
+        // A particle every quarter unit along the path.
+        for (i, x) in xs.into_iter().enumerate() {
+            assert!((x - (i + 1) as f32 / 4.).abs() < 1e-4);
+        }
+    }
+
+    /// Glowing particles that don't cool down.
+    fn blackbody_options() -> EmitterOptions {
+        EmitterOptions {
+            particle_temperature: Some(ParticleTemperature {
+                temperature: Distribution::Constant(1500.),
+                ambient_temperature: 1500.,
+                cooling_rate: 0.,
+            }),
+            particle_animation_options: Some(AnimationOptions::new(
+                1000,
+                StartAnimationAt::Zero,
+                vec![Box::new(BlackbodyAnimation {
+                    from_ms: 0,
+                    until_ms: 1000,
+                })],
+            )),
+            ..options()
+        }
+    }
+
+    #[test]
+    fn color_animations_change_the_particle_material() {
+        let mut app = emitter_app(blackbody_options);
+        app.update();
+        app.update();
+
+        let mut particles_query = app
+            .world
+            .query_filtered::<&Handle<StandardMaterial>, With<Particle>>();
+        let handles: Vec<Handle<StandardMaterial>> =
+            particles_query.iter(&app.world).cloned().collect();
+        assert_eq!(handles.len(), 5);
+
+        let materials = app
+            .world
+            .get_resource::<Assets<StandardMaterial>>()
+            .unwrap();
+
+        let expected = Vec4::from(blackbody_color(1500.));
+
+        for handle in handles {
+            let color = Vec4::from(materials.get(handle).unwrap().base_color);
+            assert!(color.distance(expected) < 1e-5);
+        }
+
+        // The emitter material keeps the particle color.
+        assert!(materials
+            .iter()
+            .any(|(_, material)| material.base_color == Color::WHITE));
+    }
+
+    #[test]
+    fn particles_stay_behind_moving_emitters() {
+        let mut app = emitter_app(options);
+        app.update();
+
+        let start = particles(&mut app);
+        assert_eq!(start.len(), 5);
+
+        move_emitter(
+            &mut app,
+            Transform {
+                translation: Vec3::new(10., 2., 0.),
+                rotation: Quat::from_rotation_y(1.),
+                scale: Vec3::splat(2.),
+            },
+        );
+        app.update();
+        app.update();
+
+        let particles = particles(&mut app);
+
+        for (entity, (position, _)) in start {
+            assert!(position.distance(particles[&entity].0) < 1e-4);
+        }
+
+        // The rendered transform is in world space too.
+        let mut global_query = app
+            .world
+            .query_filtered::<&GlobalTransform, With<Particle>>();
+        for global_transform in global_query.iter(&app.world) {
+            assert!(global_transform.translation.x < 1.);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+pub mod boids;
+pub mod bounds;
 pub mod diffusion_animation;
 pub mod emit_color_animation;
 pub mod emit_speed_animation;
@@ -7,3 +9,5 @@ pub mod emitter_animation_handler;
 pub mod loose_movement_animation;
 pub mod randomize_size_animation;
 pub mod sway_animation;
+pub mod target_shape;
+pub mod temperature;
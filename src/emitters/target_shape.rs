@@ -0,0 +1,113 @@
+use super::emitter::{Emitter, LifeCycle, Particle};
+use bevy::prelude::*;
+
+/// Position (in emitter space) a particle is pulled to by the SpringForce.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct TargetPosition(pub Vec3);
+
+/// Index of a particle in the TargetShape of its emitter. Assigned once at spawn,
+/// so targets don't shift when older particles die.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct TargetSlot(pub usize);
+
+/// Assigns the points as targets to the particles of the emitter, in spawn order.
+/// If there are more particles than points, the points are reused.
+#[derive(Debug, Component)]
+pub struct TargetShape {
+    pub points: Vec<Vec3>,
+    pub morph: Option<ShapeMorph>,
+    /// Slot of the next spawned particle.
+    next_slot: usize,
+}
+
+/// Moves the targets from the points of the shape to other points over time.
+#[derive(Debug)]
+pub struct ShapeMorph {
+    pub points: Vec<Vec3>,
+    /// Emitter elapsed ms.
+    pub from_ms: u128,
+    pub until_ms: u128,
+}
+
+impl TargetShape {
+    pub fn new(points: Vec<Vec3>) -> Self {
+        Self {
+            points,
+            morph: None,
+            next_slot: 0,
+        }
+    }
+
+    pub fn morph_to(&mut self, points: Vec<Vec3>, from_ms: u128, until_ms: u128) {
+        self.morph = Some(ShapeMorph {
+            points,
+            from_ms,
+            until_ms,
+        });
+    }
+
+    pub fn next_slot(&mut self) -> TargetSlot {
+        let slot = TargetSlot(self.next_slot);
+        self.next_slot += 1;
+        slot
+    }
+
+    pub fn target(&self, index: usize, elapsed_ms: u128) -> Option<Vec3> {
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let from = self.points[index % self.points.len()];
+
+        let morph = match &self.morph {
+            Some(morph) if !morph.points.is_empty() => morph,
+            _ => return Some(from),
+        };
+
+        let to = morph.points[index % morph.points.len()];
+
+        if elapsed_ms < morph.from_ms {
+            Some(from)
+        } else if morph.until_ms <= elapsed_ms {
+            Some(to)
+        } else {
+            let delta_current = elapsed_ms - morph.from_ms;
+            let delta_max = morph.until_ms - morph.from_ms;
+            let fraction = delta_current as f32 / delta_max as f32;
+
+            Some(from.lerp(to, fraction))
+        }
+    }
+}
+
+pub fn assign_targets_system(
+    emitter_query: Query<(&TargetShape, &LifeCycle), With<Emitter>>,
+    mut particles_query: Query<
+        (Entity, &Parent, &TargetSlot, Option<&mut TargetPosition>),
+        With<Particle>,
+    >,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    let total_elapsed_ms = time.time_since_startup().as_millis();
+
+    for (entity, parent, slot, target_position) in particles_query.iter_mut() {
+        let (target_shape, life_cycle) = match emitter_query.get(parent.0) {
+            Ok(emitter) => emitter,
+            Err(_) => continue,
+        };
+
+        let elapsed_ms = life_cycle.elapsed_ms(total_elapsed_ms);
+        let target = match target_shape.target(slot.0, elapsed_ms) {
+            Some(target) => target,
+            None => continue,
+        };
+
+        match target_position {
+            Some(mut target_position) => target_position.0 = target,
+            None => {
+                commands.entity(entity).insert(TargetPosition(target));
+            }
+        }
+    }
+}
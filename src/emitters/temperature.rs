@@ -0,0 +1,83 @@
+use super::emitter::{Emitter, Particle};
+use crate::math::distribution::Distribution;
+use bevy::prelude::*;
+
+/// Temperature of a particle in kelvin.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct Temperature(pub f32);
+
+/// Initial temperature of particles, which cools down towards the ambient temperature.
+#[derive(Debug)]
+pub struct ParticleTemperature {
+    pub temperature: Distribution<f32>,
+    pub ambient_temperature: f32,
+    /// Fraction of the difference with the ambient temperature lost per second, e.g. 0.5
+    pub cooling_rate: f32,
+}
+
+#[derive(Debug, Component)]
+pub struct ThermalOptions {
+    pub temperature: Distribution<f32>,
+    pub ambient_temperature: f32,
+    pub cooling_rate: f32,
+}
+
+impl From<ParticleTemperature> for ThermalOptions {
+    fn from(options: ParticleTemperature) -> Self {
+        Self {
+            temperature: options.temperature,
+            ambient_temperature: options.ambient_temperature,
+            cooling_rate: options.cooling_rate,
+        }
+    }
+}
+
+pub fn cool_particles_system(
+    mut particles_query: Query<(&Parent, &mut Temperature), With<Particle>>,
+    emitter_query: Query<&ThermalOptions, With<Emitter>>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+
+    for (parent, mut temperature) in particles_query.iter_mut() {
+        let options = match emitter_query.get(parent.0) {
+            Ok(options) => options,
+            Err(_) => continue,
+        };
+
+        // Newton's law of cooling, independent of the frame rate.
+        let cooled_fraction = 1. - (-options.cooling_rate * delta_seconds).exp();
+        temperature.0 += (options.ambient_temperature - temperature.0) * cooled_fraction;
+    }
+}
+
+/// Approximation of the color of a black body at a temperature between 1000 and 40000 kelvin.
+pub fn blackbody_color(kelvin: f32) -> Color {
+    let temperature = kelvin.clamp(1000., 40000.) / 100.;
+
+    let red = if temperature <= 66. {
+        255.
+    } else {
+        329.69873 * (temperature - 60.).powf(-0.13320476)
+    };
+
+    let green = if temperature <= 66. {
+        99.4708 * temperature.ln() - 161.11957
+    } else {
+        288.12216 * (temperature - 60.).powf(-0.075514846)
+    };
+
+    let blue = if 66. <= temperature {
+        255.
+    } else if temperature <= 19. {
+        0.
+    } else {
+        138.51773 * (temperature - 10.).ln() - 305.0448
+    };
+
+    Color::rgb(
+        red.clamp(0., 255.) / 255.,
+        green.clamp(0., 255.) / 255.,
+        blue.clamp(0., 255.) / 255.,
+    )
+}
@@ -0,0 +1,231 @@
+use super::sph::Fluid;
+use crate::angles::Angles;
+use crate::collisions::collider::{Collider, ColliderResponse, ColliderShape};
+use crate::emitters::emitter::{Emitter, EmitterOptions, EmitterSize, Velocity};
+use crate::forces::force_field::ALL_LAYERS;
+use crate::math::distribution::Distribution;
+use bevy::prelude::*;
+use std::time::Duration;
+
+const PARTICLE_SPACING: f32 = 0.2;
+const PARTICLE_MASS: f32 = 1.;
+
+/// Collider that is removed at a moment in time, e.g. to let fluid go.
+#[derive(Debug, Component)]
+pub struct Dam {
+    /// Total elapsed ms.
+    pub release_at_ms: u128,
+}
+
+pub fn release_dams_system(
+    dam_query: Query<(Entity, &Dam)>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    let total_elapsed_ms = time.time_since_startup().as_millis();
+
+    for (entity, dam) in dam_query.iter() {
+        if dam.release_at_ms <= total_elapsed_ms {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Test scene, fills the left side of a tank with fluid and then removes the dam
+/// that holds it back.
+pub fn spawn_dam_break(
+    commands: &mut Commands,
+    meshes: ResMut<Assets<Mesh>>,
+    materials: ResMut<Assets<StandardMaterial>>,
+    total_elapsed_ms: u128,
+) {
+    let walls = [
+        // Floor
+        (Vec3::new(0., -0.5, 0.), Vec3::new(4.5, 0.5, 1.5)),
+        (Vec3::new(-4.5, 2., 0.), Vec3::new(0.5, 2.5, 1.5)),
+        (Vec3::new(4.5, 2., 0.), Vec3::new(0.5, 2.5, 1.5)),
+        (Vec3::new(0., 2., -1.5), Vec3::new(4.5, 2.5, 0.5)),
+        (Vec3::new(0., 2., 1.5), Vec3::new(4.5, 2.5, 0.5)),
+    ];
+
+    for (center, half_extents) in walls {
+        spawn_box_collider(commands, center, half_extents);
+    }
+
+    let dam = spawn_box_collider(commands, Vec3::new(-2., 2., 0.), Vec3::new(0.1, 2.5, 1.5));
+    commands.entity(dam).insert(Dam {
+        release_at_ms: total_elapsed_ms + 5_000,
+    });
+
+    // Pours particles down between the left wall and the dam. There is at most one emission
+    // per frame, so below 50 frames per second less fluid is poured.
+    let options = EmitterOptions {
+        emitter_transform: Transform::from_xyz(-3.8, 3., -0.8),
+        emitter_size: EmitterSize {
+            length: 1.6,
+            depth: 1.6,
+        },
+        emitter_duration: Duration::from_secs(2),
+        angle_degrees: Angles::new(180., 0.),
+        diffusion_degrees: Angles::new(0., 0.),
+        emission_distortion: 0.,
+        emitter_velocity: Velocity::zero(),
+        inherit_velocity: Vec3::ZERO,
+        particle_color: Color::rgb(0.2, 0.4, 1.),
+        particles_per_emission: 10,
+        delay_between_emission_ms: 20,
+        particles_per_distance: 0.,
+        particle_lifetime: Distribution::Constant(Duration::from_secs(60)),
+        particle_radius: Distribution::Constant(PARTICLE_SPACING / 2.),
+        particle_mass: Distribution::Constant(PARTICLE_MASS),
+        particle_speed: Distribution::Constant(2.),
+        particle_friction_coefficient: Distribution::Constant(0.),
+        particle_rotation: None,
+        particle_temperature: None,
+        particle_collisions: None,
+        collider_response: Some(ColliderResponse::Slide { friction: 0.02 }),
+        substeps: None,
+        sleep: None,
+        boids: None,
+        fluid: Some(Fluid::new(PARTICLE_SPACING, PARTICLE_MASS)),
+        bounds: None,
+        target_shape: None,
+        particle_animation_options: None,
+        emitter_animation_handler: None,
+        force_handler: None,
+        force_field_layers: ALL_LAYERS,
+    };
+
+    Emitter::create(options, commands, meshes, materials, total_elapsed_ms);
+}
+
+fn spawn_box_collider(commands: &mut Commands, center: Vec3, half_extents: Vec3) -> Entity {
+    let transform = Transform::from_translation(center);
+
+    commands
+        .spawn()
+        .insert(transform)
+        .insert(GlobalTransform::from(transform))
+        .insert(Collider::new(ColliderShape::Box { half_extents }))
+        .id()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fluids::sph::FluidBody;
+    use crate::grid::Grid;
+    use bevy::utils::HashMap;
+
+    const DAM_X: f32 = -1.2;
+    const RELEASE_AT_SECONDS: f32 = 1.;
+    const DELTA_SECONDS: f32 = 1. / 60.;
+
+    /// Inner corners of the tank.
+    fn tank() -> (Vec3, Vec3) {
+        (Vec3::new(-2., 0., -0.4), Vec3::new(2., 4., 0.4))
+    }
+
+    /// Block of fluid at rest spacing between the left wall of the tank and the dam.
+    fn fluid_block() -> Vec<FluidBody> {
+        let (min, _) = tank();
+        let mut bodies = Vec::new();
+
+        for x in 0..4 {
+            for y in 0..10 {
+                for z in 0..4 {
+                    let position =
+                        min + (Vec3::new(x as f32, y as f32, z as f32) + 0.5) * PARTICLE_SPACING;
+                    bodies.push(FluidBody::new(position, Vec3::ZERO, PARTICLE_MASS));
+                }
+            }
+        }
+
+        bodies
+    }
+
+    /// Indices of the bodies within radius of each other.
+    fn pairs(bodies: &[FluidBody], radius: f32) -> Vec<(usize, usize)> {
+        let mut world = World::new();
+        let mut grid = Grid::new(radius);
+        let mut indices = HashMap::default();
+
+        for (index, body) in bodies.iter().enumerate() {
+            let entity = world.spawn().id();
+            indices.insert(entity, index);
+            grid.insert(entity, body.position);
+        }
+
+        let mut pairs = Vec::new();
+        grid.for_each_pair(radius, |a, b| {
+            pairs.push((indices[&a.entity], indices[&b.entity]))
+        });
+        pairs
+    }
+
+    /// Sliding response of the walls, like the colliders of the scene.
+    fn keep_in_tank(body: &mut FluidBody, dammed: bool) {
+        let radius = PARTICLE_SPACING / 2.;
+        let (min, mut max) = tank();
+
+        if dammed {
+            max.x = DAM_X;
+        }
+
+        for axis in 0..3 {
+            if body.position[axis] < min[axis] + radius {
+                body.position[axis] = min[axis] + radius;
+                body.velocity[axis] = body.velocity[axis].max(0.);
+            } else if max[axis] - radius < body.position[axis] {
+                body.position[axis] = max[axis] - radius;
+                body.velocity[axis] = body.velocity[axis].min(0.);
+            }
+        }
+    }
+
+    /// Steps are fixed, so the outcome doesn't depend on how fast the machine is.
+    #[test]
+    fn dam_break_stays_stable() {
+        let fluid = Fluid::new(PARTICLE_SPACING, PARTICLE_MASS);
+        let mut bodies = fluid_block();
+        let particle_count = bodies.len();
+        // Speed of a particle falling from the top of the tank.
+        let fall_speed = (2. * 9.81 * tank().1.y).sqrt();
+
+        // The dam is released after a second, the fluid comes to rest well before 20.
+        for frame in 0..1200 {
+            let dammed = (frame as f32 * DELTA_SECONDS) < RELEASE_AT_SECONDS;
+            let pairs = pairs(&bodies, fluid.smoothing_radius);
+            fluid.simulate(&mut bodies, &pairs, DELTA_SECONDS);
+
+            for body in bodies.iter_mut() {
+                keep_in_tank(body, dammed);
+
+                assert!(body.position.is_finite() && body.velocity.is_finite());
+                assert!(body.velocity.length() < fall_speed);
+            }
+        }
+
+        assert_eq!(particle_count, bodies.len());
+
+        // The fluid flowed past the dam and spread over the floor of the tank.
+        let max_x = bodies
+            .iter()
+            .map(|body| body.position.x)
+            .fold(f32::MIN, f32::max);
+        assert!(0. < max_x, "front at {}", max_x);
+
+        // And came to rest without piling up.
+        let max_speed = bodies
+            .iter()
+            .map(|body| body.velocity.length())
+            .fold(0., f32::max);
+        assert!(max_speed < 0.5, "max speed {}", max_speed);
+
+        let max_y = bodies
+            .iter()
+            .map(|body| body.position.y)
+            .fold(f32::MIN, f32::max);
+        assert!(max_y < 1., "top at {}", max_y);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod dam_break;
+pub mod sph;
@@ -0,0 +1,332 @@
+use super::dam_break::release_dams_system;
+use crate::collisions::collider::{ColliderSystem, Stuck};
+use crate::collisions::sleep::Asleep;
+use crate::emitters::emitter::{
+    velocity_to_emitter, velocity_to_world, Emitter, ParticleAttributes, Velocity,
+};
+use crate::grid::Grid;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::f32::consts::PI;
+
+/// Simulates the particles of an emitter as a fluid with smoothed-particle hydrodynamics,
+/// e.g. splashing water or goo. Fluid particles are moved by the solver in world space,
+/// forces of the emitter still change their velocity. Use a ColliderResponse to keep the fluid
+/// in, but no substeps, the solver already steps on its own.
+#[derive(Debug, Component, Clone)]
+pub struct Fluid {
+    /// Particles further apart don't interact, around twice the spacing of resting particles.
+    pub smoothing_radius: f32,
+    /// Mass per cubic unit the fluid settles at.
+    pub rest_density: f32,
+    /// How hard the fluid pushes back when compressed, stiff fluids need a shorter max_step_seconds.
+    pub stiffness: f32,
+    /// Pushes close neighbors apart, keeps particles from clumping and piling up against colliders.
+    pub near_stiffness: f32,
+    /// Low for water, high for goo.
+    pub viscosity: f32,
+    /// Pulls neighbors together, so the fluid forms drops and strings.
+    pub surface_tension: f32,
+    /// In world space, units per second squared.
+    pub gravity: Vec3,
+    /// Frames are split in steps of at most this long, the solver blows up with long steps.
+    pub max_step_seconds: f32,
+}
+
+/// Particles of an emitter with a Fluid.
+#[derive(Debug, Component)]
+pub struct FluidParticle;
+
+/// World space state of a fluid particle.
+#[derive(Debug, Clone, Copy)]
+pub struct FluidBody {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub mass: f32,
+    density: f32,
+    near_density: f32,
+    pressure: f32,
+}
+
+impl FluidBody {
+    pub fn new(position: Vec3, velocity: Vec3, mass: f32) -> Self {
+        Self {
+            position,
+            velocity,
+            mass,
+            density: 0.,
+            near_density: 0.,
+            pressure: 0.,
+        }
+    }
+
+    /// Density of the last step.
+    pub fn density(&self) -> f32 {
+        self.density
+    }
+}
+
+/// Smoothing kernels from Particle-Based Fluid Simulation for Interactive Applications
+/// by Müller et al.
+struct Kernels {
+    radius: f32,
+    poly6: f32,
+    spiky: f32,
+    spiky_gradient: f32,
+    viscosity_laplacian: f32,
+}
+
+impl Kernels {
+    fn new(radius: f32) -> Self {
+        Self {
+            radius,
+            poly6: 315. / (64. * PI * radius.powi(9)),
+            spiky: 15. / (PI * radius.powi(6)),
+            spiky_gradient: -45. / (PI * radius.powi(6)),
+            viscosity_laplacian: 45. / (PI * radius.powi(6)),
+        }
+    }
+
+    fn poly6(&self, distance: f32) -> f32 {
+        if self.radius <= distance {
+            return 0.;
+        }
+
+        self.poly6 * (self.radius.powi(2) - distance.powi(2)).powi(3)
+    }
+
+    /// Sharp at the center, used for the near density.
+    fn spiky(&self, distance: f32) -> f32 {
+        if self.radius <= distance {
+            return 0.;
+        }
+
+        self.spiky * (self.radius - distance).powi(3)
+    }
+
+    /// Length of the gradient, it points away from the neighbor.
+    fn spiky_gradient(&self, distance: f32) -> f32 {
+        if self.radius <= distance {
+            return 0.;
+        }
+
+        self.spiky_gradient * (self.radius - distance).powi(2)
+    }
+
+    fn viscosity_laplacian(&self, distance: f32) -> f32 {
+        if self.radius <= distance {
+            return 0.;
+        }
+
+        self.viscosity_laplacian * (self.radius - distance)
+    }
+}
+
+impl Fluid {
+    /// Water like defaults for particles with the given spacing when the fluid is at rest.
+    pub fn new(spacing: f32, particle_mass: f32) -> Self {
+        Self {
+            smoothing_radius: spacing * 2.,
+            rest_density: particle_mass / spacing.powi(3),
+            stiffness: 100.,
+            near_stiffness: 10.,
+            viscosity: 1.,
+            surface_tension: 0.5,
+            gravity: Vec3::new(0., -9.81, 0.),
+            max_step_seconds: 0.004,
+        }
+    }
+
+    /// Advances the bodies by delta seconds in steps of at most max_step_seconds.
+    /// Pairs are the indices of bodies within the smoothing radius of each other.
+    pub fn simulate(&self, bodies: &mut [FluidBody], pairs: &[(usize, usize)], delta_seconds: f32) {
+        let steps = (delta_seconds / self.max_step_seconds).ceil().max(1.);
+
+        for _ in 0..steps as u32 {
+            self.step(bodies, pairs, delta_seconds / steps);
+        }
+    }
+
+    fn step(&self, bodies: &mut [FluidBody], pairs: &[(usize, usize)], delta_seconds: f32) {
+        let kernels = Kernels::new(self.smoothing_radius);
+        let self_density = kernels.poly6(0.);
+
+        for body in bodies.iter_mut() {
+            body.density = body.mass * self_density;
+            body.near_density = 0.;
+        }
+
+        for &(a, b) in pairs {
+            let distance = bodies[a].position.distance(bodies[b].position);
+            let weight = kernels.poly6(distance);
+            let near_weight = kernels.spiky(distance);
+            bodies[a].density += bodies[b].mass * weight;
+            bodies[b].density += bodies[a].mass * weight;
+            bodies[a].near_density += bodies[b].mass * near_weight;
+            bodies[b].near_density += bodies[a].mass * near_weight;
+        }
+
+        // Only pushes, pulling is left to the surface tension so the fluid doesn't clump.
+        // The near pressure is from Particle-based Viscoelastic Fluid Simulation by Clavet et al.
+        for body in bodies.iter_mut() {
+            body.pressure = self.stiffness * (body.density - self.rest_density).max(0.)
+                + self.near_stiffness * body.near_density;
+        }
+
+        let mut forces = vec![Vec3::ZERO; bodies.len()];
+
+        for &(a, b) in pairs {
+            let (a_body, b_body) = (&bodies[a], &bodies[b]);
+            let offset = b_body.position - a_body.position;
+            let distance = offset.length();
+
+            if self.smoothing_radius <= distance {
+                continue;
+            }
+
+            // Particles on top of each other are pushed apart in an arbitrary direction.
+            let direction = if 0. < distance {
+                offset / distance
+            } else {
+                Vec3::Y
+            };
+
+            // Symmetric, so momentum is conserved. Sparse particles, e.g. just spawned ones that
+            // overlap, are pushed as if at rest density so they don't shoot off.
+            let pressure = direction
+                * kernels.spiky_gradient(distance)
+                * (a_body.pressure + b_body.pressure)
+                / 2.
+                * a_body.mass
+                * b_body.mass
+                / (a_body.density.max(self.rest_density) * b_body.density.max(self.rest_density));
+
+            let viscosity = (b_body.velocity - a_body.velocity)
+                * self.viscosity
+                * kernels.viscosity_laplacian(distance)
+                * a_body.mass
+                * b_body.mass
+                / (a_body.density * b_body.density);
+
+            // Cohesion from Weakly compressible SPH for free surface flows by Becker and Teschner.
+            let tension =
+                offset * self.surface_tension * a_body.mass * b_body.mass * kernels.poly6(distance);
+
+            let force = pressure + viscosity + tension;
+            forces[a] += force;
+            forces[b] -= force;
+        }
+
+        for (body, force) in bodies.iter_mut().zip(forces) {
+            let acceleration = force / body.mass + self.gravity;
+            body.velocity += acceleration * delta_seconds;
+            body.position += body.velocity * delta_seconds;
+        }
+    }
+}
+
+pub struct FluidPlugin;
+
+impl Plugin for FluidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(simulate_fluids_system.before(ColliderSystem::Collide))
+            .add_system(release_dams_system);
+    }
+}
+
+/// Fluid particles of one emitter.
+#[derive(Default)]
+struct FluidGroup {
+    entities: Vec<Entity>,
+    bodies: Vec<FluidBody>,
+    pairs: Vec<(usize, usize)>,
+}
+
+fn simulate_fluids_system(
+    mut particles_query: Query<
+        (
+            Entity,
+            &Parent,
+            &mut Transform,
+            &mut Velocity,
+            &ParticleAttributes,
+        ),
+        (With<FluidParticle>, Without<Stuck>, Without<Asleep>),
+    >,
+    emitter_query: Query<(&Fluid, &GlobalTransform), With<Emitter>>,
+    grid: Res<Grid>,
+    time: Res<Time>,
+) {
+    let mut groups: HashMap<Entity, FluidGroup> = HashMap::default();
+    // Emitter and index in the group of every fluid particle.
+    let mut indices: HashMap<Entity, (Entity, usize)> = HashMap::default();
+    let mut max_smoothing_radius: f32 = 0.;
+
+    for (entity, parent, transform, velocity, attributes) in particles_query.iter() {
+        let (fluid, emitter_transform) = match emitter_query.get(parent.0) {
+            Ok(emitter) => emitter,
+            Err(_) => continue,
+        };
+
+        max_smoothing_radius = max_smoothing_radius.max(fluid.smoothing_radius);
+
+        let group = groups.entry(parent.0).or_default();
+        indices.insert(entity, (parent.0, group.entities.len()));
+        group.entities.push(entity);
+        // From the emitter, particles spawned this frame don't have a GlobalTransform yet.
+        group.bodies.push(FluidBody::new(
+            emitter_transform.mul_vec3(transform.translation),
+            velocity_to_world(emitter_transform, velocity.to_vec3()),
+            attributes.mass,
+        ));
+    }
+
+    if groups.is_empty() {
+        return;
+    }
+
+    // Neighbors are found once per frame, the kernels ignore pairs that drift apart.
+    grid.for_each_pair(max_smoothing_radius, |a, b| {
+        let (emitter, a_index, b_index) = match (indices.get(&a.entity), indices.get(&b.entity)) {
+            (Some(&(a_emitter, a_index)), Some(&(b_emitter, b_index)))
+                if a_emitter == b_emitter =>
+            {
+                (a_emitter, a_index, b_index)
+            }
+            _ => return,
+        };
+
+        if let Some(group) = groups.get_mut(&emitter) {
+            group.pairs.push((a_index, b_index));
+        }
+    });
+
+    let delta_seconds = time.delta_seconds();
+
+    for (emitter, mut group) in groups {
+        let (fluid, emitter_transform) = match emitter_query.get(emitter) {
+            Ok(emitter) => emitter,
+            Err(_) => continue,
+        };
+
+        let start_positions: Vec<Vec3> = group.bodies.iter().map(|body| body.position).collect();
+        fluid.simulate(&mut group.bodies, &group.pairs, delta_seconds);
+
+        // Particles are children of the emitter, so the state is converted to emitter space.
+        let inverse_rotation = emitter_transform.rotation.inverse();
+
+        for ((entity, body), start_position) in
+            group.entities.iter().zip(group.bodies).zip(start_positions)
+        {
+            let (_, _, mut transform, mut velocity, _) = match particles_query.get_mut(*entity) {
+                Ok(particle) => particle,
+                Err(_) => continue,
+            };
+
+            // Moved by the offset, so corrections of other systems this frame are kept.
+            transform.translation +=
+                inverse_rotation * (body.position - start_position) / emitter_transform.scale;
+            velocity.set_vec3(velocity_to_emitter(emitter_transform, body.velocity));
+        }
+    }
+}
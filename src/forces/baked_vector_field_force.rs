@@ -0,0 +1,113 @@
+use super::force::{Force, ForceData};
+use super::vector_field_force::FieldMode;
+use crate::math::vector_grid::{OutsideBounds, VectorGrid};
+use bevy::prelude::{Handle, Transform};
+
+/// Moves particles along a VectorGrid, e.g. loaded from an FGA file.
+pub struct BakedVectorFieldForce {
+    /// E.g. asset_server.load("field.fga"), particles aren't moved until the grid is loaded.
+    /// Needs the VectorGridPlugin.
+    pub grid: Handle<VectorGrid>,
+    /// Places the grid in the space of the particle positions, emitter space for forces
+    /// of an emitter and world space for forces of a ForceField.
+    pub transform: Transform,
+    pub outside: OutsideBounds,
+    pub strength: f32,
+    pub mode: FieldMode,
+    pub from_ms: u128,
+    pub until_ms: u128,
+}
+
+impl BakedVectorFieldForce {
+    pub fn new(grid: Handle<VectorGrid>, transform: Transform, mode: FieldMode) -> Self {
+        Self {
+            grid,
+            transform,
+            outside: OutsideBounds::Clamp,
+            strength: 1.,
+            mode,
+            from_ms: 0,
+            until_ms: u128::MAX,
+        }
+    }
+}
+
+impl Force for BakedVectorFieldForce {
+    fn apply(&self, data: &mut ForceData, force_cycle_ms: u128) {
+        if force_cycle_ms < self.from_ms || self.until_ms <= force_cycle_ms {
+            return;
+        }
+
+        let grid = match data.vector_grids.and_then(|grids| grids.get(&self.grid)) {
+            Some(grid) => grid,
+            None => return,
+        };
+
+        let matrix = self.transform.compute_matrix();
+        let grid_position = matrix.inverse().transform_point3(*data.position);
+        let field_velocity =
+            matrix.transform_vector3(grid.sample(grid_position, self.outside)) * self.strength;
+
+        self.mode.apply(data, field_velocity);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::emitters::emitter::Velocity;
+    use bevy::asset::{AddAsset, AssetPlugin, Assets};
+    use bevy::core::CorePlugin;
+    use bevy::math::{UVec3, Vec3};
+    use bevy::prelude::App;
+
+    /// Velocity of a particle at the origin after the force is applied.
+    fn apply(force: &BakedVectorFieldForce, vector_grids: Option<&Assets<VectorGrid>>) -> Vec3 {
+        let mut velocity = Velocity::zero();
+        let mut data = ForceData {
+            position: &Vec3::ZERO,
+            velocity: &mut velocity,
+            radius: Vec3::ONE,
+            mass: 1.,
+            delta_seconds: 0.1,
+            total_elapsed_ms: 0,
+            target: None,
+            temperature: None,
+            vector_grids,
+        };
+
+        force.apply(&mut data, 0);
+        velocity.to_vec3()
+    }
+
+    #[test]
+    fn samples_the_loaded_grid() {
+        let mut app = App::new();
+        app.add_plugin(CorePlugin)
+            .add_plugin(AssetPlugin)
+            .add_asset::<VectorGrid>();
+
+        let grid = VectorGrid::new(UVec3::splat(2), -Vec3::ONE, Vec3::ONE, vec![Vec3::X; 8]);
+        let handle = app
+            .world
+            .get_resource_mut::<Assets<VectorGrid>>()
+            .unwrap()
+            .add(grid);
+
+        let force =
+            BakedVectorFieldForce::new(handle, Transform::identity(), FieldMode::SetVelocity);
+        let vector_grids = app.world.get_resource::<Assets<VectorGrid>>().unwrap();
+
+        assert_eq!(apply(&force, Some(vector_grids)), Vec3::X);
+        // Without the VectorGridPlugin.
+        assert_eq!(apply(&force, None), Vec3::ZERO);
+
+        // Not loaded yet.
+        let unloaded = BakedVectorFieldForce::new(
+            Handle::default(),
+            Transform::identity(),
+            FieldMode::SetVelocity,
+        );
+        assert_eq!(apply(&unloaded, Some(vector_grids)), Vec3::ZERO);
+    }
+}
@@ -0,0 +1,34 @@
+use super::force::{Force, ForceData};
+use bevy::math::Vec3;
+
+/// Hot particles rise and cold particles sink, particles without a Temperature are ignored.
+pub struct BuoyancyForce {
+    /// In newton per kelvin difference with the ambient temperature.
+    pub strength: f32,
+    pub ambient_temperature: f32,
+    pub up: Vec3,
+    pub from_ms: u128,
+    pub until_ms: u128,
+}
+
+impl Force for BuoyancyForce {
+    fn apply(&self, data: &mut ForceData, force_cycle_ms: u128) {
+        if force_cycle_ms < self.from_ms || self.until_ms <= force_cycle_ms {
+            return;
+        }
+
+        let temperature = match data.temperature {
+            Some(temperature) => temperature,
+            None => return,
+        };
+
+        let force =
+            self.up.normalize_or_zero() * self.strength * (temperature - self.ambient_temperature);
+        let delta = force / data.mass * data.delta_seconds;
+
+        let velocity = &mut data.velocity;
+        velocity.vx += delta.x;
+        velocity.vy += delta.y;
+        velocity.vz += delta.z;
+    }
+}
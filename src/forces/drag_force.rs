@@ -0,0 +1,52 @@
+use super::force::{Force, ForceData};
+use super::wind_force::WindForce;
+use bevy::math::Vec3;
+
+/**
+ * Air resistance relative to the wind, linear drag scales with the radius and quadratic drag with
+ * the cross section of the particle. So big light particles drift further than small heavy ones.
+ */
+pub struct DragForce {
+    pub linear_coefficient: f32,
+    pub quadratic_coefficient: f32,
+    /// Still air if none.
+    pub wind: Option<WindForce>,
+    pub from_ms: u128,
+    pub until_ms: u128,
+}
+
+const MS_PER_SEC: f32 = 1000.;
+
+impl Force for DragForce {
+    fn apply(&self, data: &mut ForceData, force_cycle_ms: u128) {
+        if force_cycle_ms < self.from_ms || self.until_ms <= force_cycle_ms {
+            return;
+        }
+
+        // Not the cycle time, gusts would jump back at the start of every cycle.
+        let seconds = data.total_elapsed_ms as f32 / MS_PER_SEC;
+        let wind_velocity = match &self.wind {
+            Some(wind) => wind.velocity_at(data.position, seconds),
+            None => Vec3::ZERO,
+        };
+
+        let relative_velocity = data.velocity.to_vec3() - wind_velocity;
+        let radius = data.radius.max_element();
+
+        let linear = self.linear_coefficient * radius;
+        let quadratic = self.quadratic_coefficient * radius.powi(2) * relative_velocity.length();
+        let force = -relative_velocity * (linear + quadratic);
+
+        let mut delta = force / data.mass * data.delta_seconds;
+
+        // Drag can slow particles down to the wind speed, but never push them past it.
+        if relative_velocity.length_squared() < delta.length_squared() {
+            delta = -relative_velocity;
+        }
+
+        let velocity = &mut data.velocity;
+        velocity.vx += delta.x;
+        velocity.vy += delta.y;
+        velocity.vz += delta.z;
+    }
+}
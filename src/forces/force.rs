@@ -1,8 +1,10 @@
 use std::fmt::Debug;
 
+use bevy::asset::Assets;
 use bevy::math::Vec3;
 
 use crate::emitters::emitter::Velocity;
+use crate::math::vector_grid::VectorGrid;
 
 pub trait Force {
     fn apply(&self, particle: &mut ForceData, force_cycle_ms: u128);
@@ -14,10 +16,18 @@ impl Debug for dyn Force {
     }
 }
 
-pub struct ForceData<'a, 'b> {
+pub struct ForceData<'a, 'b, 'c> {
     pub position: &'a Vec3,
     pub velocity: &'b mut Velocity,
     pub radius: Vec3,
     pub mass: f32,
     pub delta_seconds: f32,
+    /// Time since startup, for fields that evolve steadily instead of repeating every cycle.
+    pub total_elapsed_ms: u128,
+    /// TargetPosition of the particle, if any.
+    pub target: Option<Vec3>,
+    /// Temperature of the particle in kelvin, if any.
+    pub temperature: Option<f32>,
+    /// Loaded grids of the BakedVectorFieldForce, None without the VectorGridPlugin.
+    pub vector_grids: Option<&'c Assets<VectorGrid>>,
 }
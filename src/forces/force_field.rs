@@ -0,0 +1,197 @@
+use super::force::ForceData;
+use super::force_handler::{ForceHandler, ForceTime};
+use super::impulse::{apply_impulses_system, ApplyImpulse};
+use super::tracking_gravitational_force::apply_tracking_gravitational_forces_system;
+use crate::collisions::sleep::Asleep;
+use crate::emitters::emitter::{
+    velocity_to_emitter, velocity_to_world, LifeCycle, Particle, ParticleAttributes, Velocity,
+};
+use crate::emitters::temperature::Temperature;
+use crate::math::distribution::sample_curve;
+use crate::math::vector_grid::VectorGrid;
+use bevy::prelude::*;
+
+/// Standalone forces in the world, e.g. a wind zone or black hole. Particles of every emitter
+/// inside the shape are affected, if the layers of the emitter and field overlap.
+/// The shape is placed with the Transform of the entity.
+#[derive(Component)]
+pub struct ForceField {
+    pub shape: FieldShape,
+    pub falloff: Falloff,
+    pub force_handler: ForceHandler,
+    pub layers: u32,
+}
+
+/// Layer mask of an emitter, 0 opts out of all force fields.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct ForceFieldLayers(pub u32);
+
+pub const ALL_LAYERS: u32 = u32::MAX;
+
+/// Shapes are in the local space of the field entity.
+#[derive(Debug, Clone)]
+pub enum FieldShape {
+    Sphere {
+        radius: f32,
+    },
+    Box {
+        half_extents: Vec3,
+    },
+    /// Along the local y axis.
+    Capsule {
+        radius: f32,
+        half_height: f32,
+    },
+    /// Everything within distance of the local xz plane.
+    InfinitePlane {
+        distance: f32,
+    },
+}
+
+/// Strength of the field from the center (0) to the edge (1) of the shape.
+#[derive(Debug, Clone)]
+pub enum Falloff {
+    Constant,
+    Linear,
+    Smooth,
+    /// Points of (normalized distance, strength), interpolated linearly.
+    Curve(Vec<(f32, f32)>),
+}
+
+pub struct ForceFieldPlugin;
+
+impl Plugin for ForceFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ApplyImpulse>()
+            .add_system(apply_force_fields_system)
+            .add_system(apply_tracking_gravitational_forces_system)
+            .add_system(apply_impulses_system);
+    }
+}
+
+impl FieldShape {
+    /// Returns the normalized distance from 0..1 if the point is inside.
+    pub fn normalized_distance(&self, local: Vec3) -> Option<f32> {
+        let distance = match self {
+            FieldShape::Sphere { radius } => local.length() / radius,
+            FieldShape::Box { half_extents } => (local.abs() / *half_extents).max_element(),
+            FieldShape::Capsule {
+                radius,
+                half_height,
+            } => {
+                let closest_y = local.y.clamp(-half_height, *half_height);
+                (local - Vec3::new(0., closest_y, 0.)).length() / radius
+            }
+            FieldShape::InfinitePlane { distance } => local.y.abs() / distance,
+        };
+
+        if distance <= 1. {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+}
+
+impl Falloff {
+    pub fn strength(&self, normalized_distance: f32) -> f32 {
+        let d = normalized_distance;
+
+        match self {
+            Falloff::Constant => 1.,
+            Falloff::Linear => 1. - d,
+            Falloff::Smooth => 1. - d * d * (3. - 2. * d),
+            // Without points there is no falloff.
+            Falloff::Curve(points) => sample_curve(points, d, |point| *point).unwrap_or(1.),
+        }
+    }
+}
+
+fn to_local(global_transform: &GlobalTransform, point: Vec3) -> Vec3 {
+    global_transform.rotation.inverse() * (point - global_transform.translation)
+        / global_transform.scale
+}
+
+fn apply_force_fields_system(
+    mut particles_query: Query<
+        (
+            &Parent,
+            &GlobalTransform,
+            &Transform,
+            &mut Velocity,
+            &ParticleAttributes,
+            &LifeCycle,
+            Option<&Temperature>,
+        ),
+        (With<Particle>, Without<Asleep>),
+    >,
+    emitter_query: Query<(&GlobalTransform, &ForceFieldLayers)>,
+    field_query: Query<(&GlobalTransform, &ForceField)>,
+    vector_grids: Option<Res<Assets<VectorGrid>>>,
+    time: Res<Time>,
+) {
+    if field_query.iter().next().is_none() {
+        return;
+    }
+
+    let total_elapsed_ms = time.time_since_startup().as_millis();
+    let delta_seconds = time.delta_seconds();
+
+    for (parent, global_transform, transform, mut velocity, attributes, life_cycle, temperature) in
+        particles_query.iter_mut()
+    {
+        let (emitter_transform, layers) = match emitter_query.get(parent.0) {
+            Ok(emitter) => emitter,
+            Err(_) => continue,
+        };
+
+        // Fields live in world space, particles in emitter space.
+        let position = global_transform.translation;
+        let mut world_velocity =
+            Velocity::from_vec3(velocity_to_world(emitter_transform, velocity.to_vec3()));
+
+        // Fields are not part of an emitter, so the emitter cycle is the time since startup.
+        let time = ForceTime {
+            emitter_elapsed_ms: total_elapsed_ms,
+            particle_age_ms: life_cycle.elapsed_ms(total_elapsed_ms),
+            particle_lifetime_ms: life_cycle.duration_ms(),
+        };
+
+        for (field_transform, field) in field_query.iter() {
+            if field.layers & layers.0 == 0 {
+                continue;
+            }
+
+            let local = to_local(field_transform, position);
+            let normalized_distance = match field.shape.normalized_distance(local) {
+                Some(normalized_distance) => normalized_distance,
+                None => continue,
+            };
+
+            let strength = field.falloff.strength(normalized_distance);
+            let before = world_velocity.to_vec3();
+
+            let mut data = ForceData {
+                position: &position,
+                velocity: &mut world_velocity,
+                radius: transform.scale,
+                mass: attributes.mass,
+                delta_seconds,
+                total_elapsed_ms,
+                target: None,
+                temperature: temperature.map(|temperature| temperature.0),
+                vector_grids: vector_grids.as_deref(),
+            };
+
+            field.force_handler.apply(&mut data, &time);
+
+            let delta = (world_velocity.to_vec3() - before) * strength;
+            world_velocity.set_vec3(before + delta);
+        }
+
+        velocity.set_vec3(velocity_to_emitter(
+            emitter_transform,
+            world_velocity.to_vec3(),
+        ));
+    }
+}
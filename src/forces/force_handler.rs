@@ -8,7 +8,30 @@ use super::force::ForceData;
 pub struct ForceHandler {
     pub duration_ms: u128,
     pub lifetime: Instant,
-    pub forces: Vec<Box<dyn Force + Sync + Send>>,
+    pub forces: Vec<ScheduledForce>,
+}
+
+pub struct ScheduledForce {
+    pub force: Box<dyn Force + Sync + Send>,
+    pub time_basis: TimeBasis,
+}
+
+/// What the from_ms / until_ms of a force are relative to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeBasis {
+    /// Elapsed time of the emitter, repeats every duration_ms of the handler.
+    EmitterCycle,
+    /// Time since the particle spawned.
+    ParticleAge,
+    /// Particle age from 0..1 of its lifetime, scaled to the duration_ms of the handler.
+    /// E.g. with a duration of 1000 ms, until_ms: 500 is the first half of the particle's life.
+    NormalizedParticleAge,
+}
+
+pub struct ForceTime {
+    pub emitter_elapsed_ms: u128,
+    pub particle_age_ms: u128,
+    pub particle_lifetime_ms: u128,
 }
 
 impl ForceHandler {
@@ -21,14 +44,30 @@ impl ForceHandler {
     }
 
     pub fn add(&mut self, force: Box<dyn Force + Sync + Send>) {
-        self.forces.push(force);
+        self.add_with_time_basis(force, TimeBasis::EmitterCycle);
+    }
+
+    pub fn add_with_time_basis(
+        &mut self,
+        force: Box<dyn Force + Sync + Send>,
+        time_basis: TimeBasis,
+    ) {
+        self.forces.push(ScheduledForce { force, time_basis });
     }
 
-    pub fn apply(&self, data: &mut ForceData, elapsed_ms: u128) {
-        let forces_cycle_ms = elapsed_ms % self.duration_ms;
+    pub fn apply(&self, data: &mut ForceData, time: &ForceTime) {
+        for scheduled in self.forces.iter() {
+            let force_cycle_ms = match scheduled.time_basis {
+                TimeBasis::EmitterCycle => time.emitter_elapsed_ms % self.duration_ms,
+                TimeBasis::ParticleAge => time.particle_age_ms,
+                TimeBasis::NormalizedParticleAge => {
+                    let fraction =
+                        time.particle_age_ms as f32 / time.particle_lifetime_ms.max(1) as f32;
+                    (fraction.min(1.) * self.duration_ms as f32) as u128
+                }
+            };
 
-        for force in self.forces.iter() {
-            force.apply(data, forces_cycle_ms);
+            scheduled.force.apply(data, force_cycle_ms);
         }
     }
 }
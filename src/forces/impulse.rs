@@ -0,0 +1,51 @@
+use super::force_field::{Falloff, ForceFieldLayers};
+use crate::emitters::emitter::{velocity_to_emitter, Particle, ParticleAttributes, Velocity};
+use crate::grid::Grid;
+use bevy::prelude::*;
+
+/// Event that pushes particles away from the center at once, e.g. an explosion or a footstep.
+/// A negative strength pulls particles in.
+#[derive(Debug, Clone)]
+pub struct ApplyImpulse {
+    pub center: Vec3,
+    pub radius: f32,
+    /// In newton seconds, heavier particles get less velocity.
+    pub strength: f32,
+    pub falloff: Falloff,
+    /// Only affects emitters with overlapping force field layers.
+    pub layers: u32,
+}
+
+pub fn apply_impulses_system(
+    mut events: EventReader<ApplyImpulse>,
+    mut particles_query: Query<(&Parent, &mut Velocity, &ParticleAttributes), With<Particle>>,
+    emitter_query: Query<(&GlobalTransform, &ForceFieldLayers)>,
+    grid: Res<Grid>,
+) {
+    for impulse in events.iter() {
+        for entry in grid.query_radius(impulse.center, impulse.radius) {
+            let (parent, mut velocity, attributes) = match particles_query.get_mut(entry.entity) {
+                Ok(particle) => particle,
+                Err(_) => continue,
+            };
+
+            let (emitter_transform, layers) = match emitter_query.get(parent.0) {
+                Ok(emitter) => emitter,
+                Err(_) => continue,
+            };
+
+            if impulse.layers & layers.0 == 0 {
+                continue;
+            }
+
+            let offset = entry.position - impulse.center;
+            let normalized_distance = offset.length() / impulse.radius;
+            let strength = impulse.strength * impulse.falloff.strength(normalized_distance);
+            let delta = offset.normalize_or_zero() * strength / attributes.mass;
+
+            // Impulses are in world space, particles in emitter space.
+            let new_velocity = velocity.to_vec3() + velocity_to_emitter(emitter_transform, delta);
+            velocity.set_vec3(new_velocity);
+        }
+    }
+}
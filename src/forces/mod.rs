@@ -1,5 +1,16 @@
 pub mod accelerating_force;
+pub mod baked_vector_field_force;
+pub mod buoyancy_force;
 pub mod constant_force;
+pub mod drag_force;
 pub mod force;
+pub mod force_field;
 pub mod force_handler;
 pub mod gravitational_force;
+pub mod impulse;
+pub mod spring_force;
+pub mod tracking_gravitational_force;
+pub mod turbulence_force;
+pub mod vector_field_force;
+pub mod vortex_force;
+pub mod wind_force;
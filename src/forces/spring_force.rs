@@ -0,0 +1,33 @@
+use super::force::{Force, ForceData};
+
+/// Damped spring that pulls particles to their TargetPosition, particles without one are ignored.
+pub struct SpringForce {
+    /// In newton per unit of distance.
+    pub stiffness: f32,
+    /// In newton per unit of speed.
+    pub damping: f32,
+    pub from_ms: u128,
+    pub until_ms: u128,
+}
+
+impl Force for SpringForce {
+    fn apply(&self, data: &mut ForceData, force_cycle_ms: u128) {
+        if force_cycle_ms < self.from_ms || self.until_ms <= force_cycle_ms {
+            return;
+        }
+
+        let target = match data.target {
+            Some(target) => target,
+            None => return,
+        };
+
+        let displacement = target - *data.position;
+        let force = displacement * self.stiffness - data.velocity.to_vec3() * self.damping;
+        let delta = force / data.mass * data.delta_seconds;
+
+        let velocity = &mut data.velocity;
+        velocity.vx += delta.x;
+        velocity.vy += delta.y;
+        velocity.vz += delta.z;
+    }
+}
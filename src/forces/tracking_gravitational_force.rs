@@ -0,0 +1,122 @@
+use super::force_field::ForceFieldLayers;
+use crate::collisions::sleep::Asleep;
+use crate::emitters::emitter::{velocity_to_emitter, Dead, Particle, ParticleAttributes, Velocity};
+use bevy::prelude::*;
+
+/// Gravitational attraction towards the GlobalTransform of the entity it is attached to,
+/// e.g. particles swirling into a hand. A negative gravitational force repels particles.
+#[derive(Debug, Component)]
+pub struct TrackingGravitationalForce {
+    /// In newton
+    pub gravitational_force: f32,
+    pub mass: f32,
+    /// Smooths out the pull close to the center instead of a dead zone, e.g. 1.
+    pub softening: f32,
+    /// Particles closer than this distance are removed.
+    pub kill_radius: Option<f32>,
+    /// Units per second squared.
+    pub max_acceleration: f32,
+    pub layers: u32,
+}
+
+impl TrackingGravitationalForce {
+    fn acceleration(&self, offset: Vec3) -> Vec3 {
+        let distance_pow = offset.length_squared();
+        let softened_distance_pow = distance_pow + self.softening.powi(2);
+
+        if softened_distance_pow == 0. {
+            return Vec3::ZERO;
+        }
+
+        // Particle mass cancels out: a = G * M / (d^2 + e^2)
+        let acceleration = self.gravitational_force * self.mass / softened_distance_pow;
+        // Not clamp, that panics on a negative or NaN max.
+        let max_acceleration = self.max_acceleration.abs();
+        let acceleration = acceleration.max(-max_acceleration).min(max_acceleration);
+
+        offset.normalize_or_zero() * acceleration
+    }
+}
+
+pub fn apply_tracking_gravitational_forces_system(
+    mut particles_query: Query<
+        (Entity, &Parent, &GlobalTransform, &mut Velocity),
+        (
+            With<Particle>,
+            With<ParticleAttributes>,
+            Without<Asleep>,
+            Without<Dead>,
+        ),
+    >,
+    emitter_query: Query<(&GlobalTransform, &ForceFieldLayers)>,
+    attractor_query: Query<(&GlobalTransform, &TrackingGravitationalForce)>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    if attractor_query.iter().next().is_none() {
+        return;
+    }
+
+    let delta_seconds = time.delta_seconds();
+
+    for (entity, parent, global_transform, mut velocity) in particles_query.iter_mut() {
+        let (emitter_transform, layers) = match emitter_query.get(parent.0) {
+            Ok(emitter) => emitter,
+            Err(_) => continue,
+        };
+
+        let position = global_transform.translation;
+        let mut acceleration = Vec3::ZERO;
+
+        for (attractor_transform, attractor) in attractor_query.iter() {
+            if attractor.layers & layers.0 == 0 {
+                continue;
+            }
+
+            let offset = attractor_transform.translation - position;
+
+            if let Some(kill_radius) = attractor.kill_radius {
+                if offset.length_squared() < kill_radius.powi(2) {
+                    commands.entity(entity).insert(Dead);
+                    break;
+                }
+            }
+
+            acceleration += attractor.acceleration(offset);
+        }
+
+        // Attractors live in world space, particles in emitter space.
+        let local_acceleration = velocity_to_emitter(emitter_transform, acceleration);
+        let new_velocity = velocity.to_vec3() + local_acceleration * delta_seconds;
+        velocity.set_vec3(new_velocity);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn attractor(max_acceleration: f32) -> TrackingGravitationalForce {
+        TrackingGravitationalForce {
+            gravitational_force: 10.,
+            mass: 10.,
+            softening: 1.,
+            kill_radius: None,
+            max_acceleration,
+            layers: 1,
+        }
+    }
+
+    #[test]
+    fn limits_the_acceleration() {
+        let offset = Vec3::new(0., 0., 2.);
+
+        assert_eq!(attractor(5.).acceleration(offset), Vec3::new(0., 0., 5.));
+        assert_eq!(attractor(-5.).acceleration(offset), Vec3::new(0., 0., 5.));
+        assert_eq!(attractor(100.).acceleration(offset), Vec3::new(0., 0., 20.));
+        assert_eq!(
+            attractor(f32::NAN).acceleration(offset),
+            Vec3::new(0., 0., 20.)
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use super::force::{Force, ForceData};
+use crate::math::noise;
+use bevy::math::Vec3;
+
+/**
+ * Pushes particles along a time-evolving curl-noise field, nearby particles get a similar push so
+ * they swirl together like smoke.
+ */
+pub struct TurbulenceForce {
+    /// Noise scale, higher values give smaller swirls, e.g. 0.1
+    pub frequency: f32,
+    /// In newton
+    pub amplitude: f32,
+    /// Noise layers, each octave adds finer detail.
+    pub octaves: u32,
+    /// Units per second the noise field moves through space.
+    pub scroll_speed: Vec3,
+    pub from_ms: u128,
+    pub until_ms: u128,
+}
+
+const MS_PER_SEC: f32 = 1000.;
+
+impl Force for TurbulenceForce {
+    fn apply(&self, data: &mut ForceData, force_cycle_ms: u128) {
+        if force_cycle_ms < self.from_ms || self.until_ms <= force_cycle_ms {
+            return;
+        }
+
+        // Not the cycle time, the field would jump back at the start of every cycle.
+        let seconds = data.total_elapsed_ms as f32 / MS_PER_SEC;
+        let point = *data.position * self.frequency - self.scroll_speed * seconds;
+        let curl = noise::curl(point, self.octaves);
+
+        let velocity = &mut data.velocity;
+        let delta = curl * self.amplitude / data.mass * data.delta_seconds;
+        velocity.vx += delta.x;
+        velocity.vy += delta.y;
+        velocity.vz += delta.z;
+    }
+}
@@ -0,0 +1,101 @@
+use super::force::{Force, ForceData};
+use bevy::math::Vec3;
+
+/// Moves particles along a field of vectors, e.g. to trace chaotic attractors.
+pub struct VectorFieldForce {
+    pub field: VectorField,
+    pub mode: FieldMode,
+    /// Emitter space position of the field origin.
+    pub offset: Vec3,
+    /// Size of one field unit in emitter space.
+    pub scale: f32,
+    /// Multiplies the field values, attractors move slowly at a speed of 1.
+    pub speed: f32,
+    pub from_ms: u128,
+    pub until_ms: u128,
+}
+
+const MS_PER_SEC: f32 = 1000.;
+
+pub enum VectorField {
+    /// Classic values are sigma 10, rho 28 and beta 8/3.
+    Lorenz { sigma: f32, rho: f32, beta: f32 },
+    /// Classic values are a 0.2, b 0.2 and c 5.7.
+    Rossler { a: f32, b: f32, c: f32 },
+    /// Classic values are a 0.95, b 0.7, c 0.6, d 3.5, e 0.25 and f 0.1.
+    Aizawa {
+        a: f32,
+        b: f32,
+        c: f32,
+        d: f32,
+        e: f32,
+        f: f32,
+    },
+    /// Classic value of b is 0.208186.
+    Thomas { b: f32 },
+    /// Field value at a position in field units and the force cycle time in seconds.
+    Custom(Box<dyn Fn(Vec3, f32) -> Vec3 + Sync + Send>),
+}
+
+pub enum FieldMode {
+    /// Particles move exactly with the field.
+    SetVelocity,
+    /// Particles are accelerated towards the field value, a higher responsiveness follows the field closer.
+    Accelerate { responsiveness: f32 },
+}
+
+impl FieldMode {
+    pub fn apply(&self, data: &mut ForceData, field_velocity: Vec3) {
+        let velocity = data.velocity.to_vec3();
+        let new_velocity = match self {
+            FieldMode::SetVelocity => field_velocity,
+            FieldMode::Accelerate { responsiveness } => {
+                let fraction = 1. - (-responsiveness * data.delta_seconds).exp();
+                velocity + (field_velocity - velocity) * fraction
+            }
+        };
+
+        data.velocity.set_vec3(new_velocity);
+    }
+}
+
+impl VectorField {
+    pub fn value_at(&self, p: Vec3, seconds: f32) -> Vec3 {
+        match self {
+            VectorField::Lorenz { sigma, rho, beta } => Vec3::new(
+                sigma * (p.y - p.x),
+                p.x * (rho - p.z) - p.y,
+                p.x * p.y - beta * p.z,
+            ),
+            VectorField::Rossler { a, b, c } => {
+                Vec3::new(-p.y - p.z, p.x + a * p.y, b + p.z * (p.x - c))
+            }
+            VectorField::Aizawa { a, b, c, d, e, f } => Vec3::new(
+                (p.z - b) * p.x - d * p.y,
+                d * p.x + (p.z - b) * p.y,
+                c + a * p.z - p.z.powi(3) / 3. - (p.x * p.x + p.y * p.y) * (1. + e * p.z)
+                    + f * p.z * p.x.powi(3),
+            ),
+            VectorField::Thomas { b } => Vec3::new(
+                p.y.sin() - b * p.x,
+                p.z.sin() - b * p.y,
+                p.x.sin() - b * p.z,
+            ),
+            VectorField::Custom(field) => field(p, seconds),
+        }
+    }
+}
+
+impl Force for VectorFieldForce {
+    fn apply(&self, data: &mut ForceData, force_cycle_ms: u128) {
+        if force_cycle_ms < self.from_ms || self.until_ms <= force_cycle_ms {
+            return;
+        }
+
+        let seconds = force_cycle_ms as f32 / MS_PER_SEC;
+        let field_position = (*data.position - self.offset) / self.scale;
+        let field_velocity = self.field.value_at(field_position, seconds) * self.scale * self.speed;
+
+        self.mode.apply(data, field_velocity);
+    }
+}
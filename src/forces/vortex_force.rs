@@ -0,0 +1,49 @@
+use super::force::{Force, ForceData};
+use bevy::math::Vec3;
+
+/// Spins particles around an axis, e.g. tornadoes, whirlpools and portals.
+pub struct VortexForce {
+    /// Point on the axis.
+    pub origin: Vec3,
+    pub axis: Vec3,
+    /// In newton, positive spins counter clockwise looking down the axis.
+    pub tangential_force: f32,
+    /// In newton, pulls particles towards the axis, negative pushes outwards.
+    pub inward_force: f32,
+    /// In newton, pushes particles along the axis.
+    pub axial_force: f32,
+    /// Distance to the axis at which the force fades to zero.
+    pub falloff_radius: f32,
+    pub from_ms: u128,
+    pub until_ms: u128,
+}
+
+impl Force for VortexForce {
+    fn apply(&self, data: &mut ForceData, force_cycle_ms: u128) {
+        if force_cycle_ms < self.from_ms || self.until_ms <= force_cycle_ms {
+            return;
+        }
+
+        let axis = self.axis.normalize_or_zero();
+        let offset = *data.position - self.origin;
+        let radial = offset - axis * offset.dot(axis);
+        let distance = radial.length();
+
+        if distance == 0. || self.falloff_radius <= distance {
+            return;
+        }
+
+        let falloff = 1. - distance / self.falloff_radius;
+        let inward = -radial / distance;
+        let tangent = axis.cross(radial) / distance;
+
+        let force =
+            tangent * self.tangential_force + inward * self.inward_force + axis * self.axial_force;
+
+        let delta = force * falloff / data.mass * data.delta_seconds;
+        let velocity = &mut data.velocity;
+        velocity.vx += delta.x;
+        velocity.vy += delta.y;
+        velocity.vz += delta.z;
+    }
+}
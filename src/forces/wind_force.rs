@@ -0,0 +1,25 @@
+use crate::math::noise;
+use bevy::math::Vec3;
+
+const GUST_OCTAVES: u32 = 2;
+
+/// Velocity field of the air, used by the DragForce.
+/// Gusts are noise that travels along with the wind.
+pub struct WindForce {
+    pub direction: Vec3,
+    /// Units per second.
+    pub speed: f32,
+    /// Max extra units per second gusts add in any direction.
+    pub gust_speed: f32,
+    /// Noise scale of the gusts, higher values give smaller gusts, e.g. 0.05
+    pub gust_frequency: f32,
+}
+
+impl WindForce {
+    pub fn velocity_at(&self, position: &Vec3, seconds: f32) -> Vec3 {
+        let base = self.direction.normalize_or_zero() * self.speed;
+        let point = (*position - base * seconds) * self.gust_frequency;
+
+        base + noise::fractal_vec3(point, GUST_OCTAVES) * self.gust_speed
+    }
+}
@@ -1,606 +1,390 @@
-use crate::forces::force::ForceData;
-use crate::forces::force_handler::ForceHandler;
-use crate::particle::Particle;
+use crate::emitters::emitter::Particle;
 use bevy::prelude::*;
-use std::time::Instant;
+use bevy::utils::HashMap;
 
-#[derive(Debug)]
+const DEFAULT_CELL_SIZE: f32 = 1.;
+
+/// 3D spatial hash of all particles in world space, updated every frame.
+/// Cells should be around the size of the largest neighbor query radius.
 pub struct Grid {
-    //pub possibility_spots: Vec<Vec<Particle>>,
-    pub cell_x_count: usize,
-    pub cell_y_count: usize,
-    pub cell_z_count: usize,
-    pub possibility_x_count: usize,
-    pub possibility_y_count: usize,
-    pub possibility_z_count: usize,
-    pub possibility_side_length: usize,
-    //pub position: Position,
-    pub frame: u64,
-    pub cell_width: usize,
-    pub cell_height: usize,
-    pub duration: u128,
-    pub fps: i32,
-    pub particle_count: u32,
-    pub emitted_particle_count: u32,
-    //pub force_handler: Option<ForceHandler>,
-    //pub emitters: Vec<Emitter>,
-    pub lifetime: Instant,
+    pub cell_size: f32,
+    cells: HashMap<IVec3, Vec<GridEntry>>,
+    /// Cell of every particle in the grid.
+    locations: HashMap<Entity, IVec3>,
+    pub particle_count: usize,
 }
 
-pub struct GridOptions {
-    pub cell_x_count: usize,
-    pub cell_y_count: usize,
-    pub cell_z_count: usize,
-    pub possibility_x_count: usize,
-    pub possibility_y_count: usize,
-    pub possibility_z_count: usize,
-    pub possibility_side_length: usize,
-    //pub position: Position,
-    //pub force_handler: Option<ForceHandler>,
+#[derive(Debug, Clone, Copy)]
+pub struct GridEntry {
+    pub entity: Entity,
+    pub position: Vec3,
 }
 
-//fn create_possibility_grid(
-//possibility_x_count: usize,
-//possiblity_y_count: usize,
-//possibility_z_count: usize,
-//) -> Vec<Vec<Particle>> {
-//let mut spots: Vec<Vec<Particle>> = Vec::new();
+pub struct GridPlugin;
 
-//for _ in 0..possibility_x_count {
-//for _ in 0..possiblity_y_count {
-//spots.push(Vec::new());
-//}
-//}
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Grid>().add_system(update_grid_system);
+    }
+}
 
-//spots
-//}
+impl Default for Grid {
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
 
 impl Grid {
-    pub fn new(options: GridOptions) -> Self {
-        let GridOptions {
-            cell_x_count,
-            cell_y_count,
-            cell_z_count,
-            possibility_x_count,
-            possibility_y_count,
-            possibility_z_count,
-            possibility_side_length,
-            //force_handler,
-        } = options;
-        let cell_width = possibility_x_count * possibility_side_length;
-        let cell_height = possibility_y_count * possibility_side_length;
-        //position.width = (cell_x_count * cell_width) as f32;
-        //position.height = (cell_y_count * cell_height) as f32;
-
-        //let possibility_spots = create_possibility_grid(possibility_x_count, possibility_y_count);
-
+    pub fn new(cell_size: f32) -> Self {
         Self {
-            cell_x_count,
-            cell_y_count,
-            cell_z_count,
-            possibility_x_count,
-            possibility_y_count,
-            possibility_z_count,
-            possibility_side_length,
-            //possibility_spots,
-            cell_width,
-            cell_height,
-            frame: 0,
-            duration: 0,
-            fps: 0,
+            cell_size,
+            cells: HashMap::default(),
+            locations: HashMap::default(),
             particle_count: 0,
-            emitted_particle_count: 0,
-            //force_handler,
-            //emitters: Vec::new(),
-            lifetime: Instant::now(),
         }
     }
 
-    fn cell_x_index(&self, x_coord: f32) -> usize {
-        x_coord as usize / self.cell_width
+    pub fn cell_index(&self, position: Vec3) -> IVec3 {
+        (position / self.cell_size).floor().as_ivec3()
+    }
+
+    pub fn clear(&mut self) {
+        // Keeps the allocations of cells that were used last frame.
+        self.cells.retain(|_, cell| !cell.is_empty());
+
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+
+        self.locations.clear();
+        self.particle_count = 0;
+    }
+
+    /// Adds the particle, or moves it when it is already in the grid.
+    pub fn insert(&mut self, entity: Entity, position: Vec3) {
+        let index = self.cell_index(position);
+
+        if let Some(&old_index) = self.locations.get(&entity) {
+            if old_index == index {
+                if let Some(entry) = self.entry_mut(index, entity) {
+                    entry.position = position;
+                    return;
+                }
+            }
+
+            self.remove(entity);
+        }
+
+        self.cells
+            .entry(index)
+            .or_insert_with(Vec::new)
+            .push(GridEntry { entity, position });
+        self.locations.insert(entity, index);
+        self.particle_count += 1;
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> bool {
+        let index = match self.locations.remove(&entity) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        if let Some(cell) = self.cells.get_mut(&index) {
+            if let Some(i) = cell.iter().position(|entry| entry.entity == entity) {
+                cell.swap_remove(i);
+            }
+
+            // Empty cells are dropped, so the map doesn't grow with every cell a particle visited.
+            if cell.is_empty() {
+                self.cells.remove(&index);
+            }
+        }
+
+        self.particle_count -= 1;
+        true
+    }
+
+    /// Removes every particle for which keep returns false, e.g. despawned particles.
+    pub fn retain(&mut self, mut keep: impl FnMut(Entity) -> bool) {
+        let locations = &mut self.locations;
+        let mut removed = 0;
+
+        self.cells.retain(|_, cell| {
+            cell.retain(|entry| {
+                let kept = keep(entry.entity);
+
+                if !kept {
+                    locations.remove(&entry.entity);
+                    removed += 1;
+                }
+
+                kept
+            });
+
+            !cell.is_empty()
+        });
+
+        self.particle_count -= removed;
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&GridEntry> {
+        let index = self.locations.get(&entity)?;
+        self.cells
+            .get(index)?
+            .iter()
+            .find(|entry| entry.entity == entity)
     }
 
-    fn cell_y_index(&self, y_coord: f32) -> usize {
-        y_coord as usize / self.cell_height
+    fn entry_mut(&mut self, index: IVec3, entity: Entity) -> Option<&mut GridEntry> {
+        self.cells
+            .get_mut(&index)?
+            .iter_mut()
+            .find(|entry| entry.entity == entity)
     }
 
-    fn possibility_x_index(&self, x_coord: f32) -> usize {
-        let x_residual = x_coord as usize % self.cell_width;
-        x_residual / self.possibility_side_length
+    /// Particles within radius of the center.
+    pub fn query_radius(&self, center: Vec3, radius: f32) -> Vec<GridEntry> {
+        let radius_pow = radius.powi(2);
+
+        self.query_cells(center - Vec3::splat(radius), center + Vec3::splat(radius))
+            .filter(|entry| entry.position.distance_squared(center) <= radius_pow)
+            .collect()
     }
 
-    fn possibility_y_index(&self, y_coord: f32) -> usize {
-        let y_residual = y_coord as usize % self.cell_height;
-        y_residual / self.possibility_side_length
+    /// Particles inside the axis aligned box between min and max.
+    pub fn query_aabb(&self, min: Vec3, max: Vec3) -> Vec<GridEntry> {
+        self.query_cells(min, max)
+            .filter(|entry| min.cmple(entry.position).all() && entry.position.cmple(max).all())
+            .collect()
     }
 
-    fn possibility_index(&self, x_index: usize, y_index: usize) -> usize {
-        self.possibility_x_count * y_index + x_index
+    /// Entries of all cells overlapping the box between min and max.
+    fn query_cells(&self, min: Vec3, max: Vec3) -> Box<dyn Iterator<Item = GridEntry> + '_> {
+        let min = self.cell_index(min);
+        let max = self.cell_index(max);
+        let cell_count = (0..3)
+            .map(|axis| max[axis] as i64 - min[axis] as i64 + 1)
+            .fold(1_i64, i64::saturating_mul);
+
+        // Large boxes, e.g. a huge radius, go over the occupied cells instead of every cell.
+        if self.cells.len() as i64 <= cell_count {
+            return Box::new(
+                self.cells
+                    .iter()
+                    .filter(move |(index, _)| min.cmple(**index).all() && index.cmple(max).all())
+                    .flat_map(|(_, cell)| cell.iter().copied()),
+            );
+        }
+
+        Box::new(
+            (min.x..=max.x)
+                .flat_map(move |x| (min.y..=max.y).map(move |y| (x, y)))
+                .flat_map(move |(x, y)| (min.z..=max.z).map(move |z| IVec3::new(x, y, z)))
+                .filter_map(move |index| self.cells.get(&index))
+                .flat_map(|cell| cell.iter().copied()),
+        )
     }
 
-    //fn vec_spot_particle(&self, particle: &Particle) -> usize {
-    //let new_x_spot = self.possibility_x_index(particle.x);
-    //let new_y_spot = self.possibility_y_index(particle.y);
-
-    //self.possibility_index(new_x_spot, new_y_spot)
-    //}
-
-    //fn has_collision(
-    //&mut self,
-    //particle: &mut Particle,
-    //data: &mut CollisionData,
-    //vec_index: usize,
-    //) -> bool {
-    //for other in self.possibility_spots[vec_index].iter_mut() {
-    //if particle.handle_possible_collision(other, data) {
-    //return true;
-    //}
-    //}
-    //false
-    //}
-
-    //fn handle_collision(&mut self, particle: &mut Particle, data: &mut CollisionData) -> usize {
-    //let CollisionData {
-    //new_x,
-    //new_y,
-    //end_new_x,
-    //end_new_y,
-    //} = *data;
-
-    //let new_x_spot = self.possibility_x_index(new_x);
-    //let new_y_spot = self.possibility_y_index(new_y);
-
-    //let new_vec_index = self.possibility_index(new_x_spot, new_y_spot);
-
-    //if self.has_collision(particle, data, new_vec_index) {
-    //return self.vec_spot_particle(particle);
-    //}
-
-    //let end_x_spot = self.possibility_x_index(end_new_x);
-    //let end_y_spot = self.possibility_y_index(end_new_y);
-
-    //let has_diff_end_x_spot = end_x_spot != new_x_spot;
-    //let has_diff_end_y_spot = end_y_spot != new_y_spot;
-
-    //if has_diff_end_x_spot {
-    //let new_vec_index = self.possibility_index(end_x_spot, new_y_spot);
-
-    //if self.has_collision(particle, data, new_vec_index) {
-    //return self.vec_spot_particle(particle);
-    //}
-    //}
-
-    //if has_diff_end_y_spot {
-    //let new_vec_index = self.possibility_index(new_x_spot, end_y_spot);
-
-    //if self.has_collision(particle, data, new_vec_index) {
-    //return self.vec_spot_particle(particle);
-    //}
-    //}
-
-    //if has_diff_end_x_spot && has_diff_end_y_spot {
-    //let new_vec_index = self.possibility_index(end_x_spot, end_y_spot);
-
-    //if self.has_collision(particle, data, new_vec_index) {
-    //return self.vec_spot_particle(particle);
-    //}
-    //}
-
-    //new_vec_index
-    //}
-
-    //fn update_spot(&mut self, vec_index: usize, spot_index: usize, elapsed_ms: u128) {
-    //let mut particle = self.possibility_spots[vec_index].swap_remove(spot_index);
-
-    //let mut data = ForceData {
-    //x: particle.x,
-    //y: particle.y,
-    //vx: particle.vx,
-    //vy: particle.vy,
-    //radius: particle.radius,
-    //mass: particle.mass,
-    //};
-
-    //if let Some(force_handler) = &mut self.force_handler {
-    //force_handler.apply(&mut data, elapsed_ms);
-    //particle.vx = data.vx;
-    //particle.vy = data.vy;
-    //}
-
-    //let new_vec_index = self.update_particle(&mut particle);
-
-    //if new_vec_index != vec_index {
-    //particle.queue_frame = self.frame;
-    //}
-
-    //self.possibility_spots[new_vec_index].push(particle);
-    //}
-
-    //**
-    //* returns true if index needs to be incremented
-    //*/
-    //fn update_particle(&mut self, particle: &mut Particle) -> usize {
-    //let new_x = particle.x + particle.vx;
-    //let new_y = particle.y + particle.vy;
-    //let end_new_x = new_x + particle.diameter;
-    //let end_new_y = new_y + particle.diameter;
-
-    //let x_out_of_bounds = new_x < 0. || self.position.width <= end_new_x;
-    //let y_out_of_bounds = new_y < 0. || self.position.height <= end_new_y;
-
-    //// Inverse direction.
-    //let elasticity_force = -1. * particle.elasticity;
-
-    //if x_out_of_bounds {
-    //particle.vx *= elasticity_force;
-    //}
-
-    //if y_out_of_bounds {
-    //particle.vy *= elasticity_force;
-    //}
-
-    //particle.apply_friction();
-    //particle.animate();
-
-    //let mut data = CollisionData {
-    //new_x,
-    //new_y,
-    //end_new_x,
-    //end_new_y,
-    //};
-
-    //let new_vec_index = self.handle_collision(particle, &mut data);
-
-    //particle.transform(self.position.width, self.position.height);
-    //particle.draw(&self.position);
-
-    //new_vec_index
-    //}
-
-    //pub fn fill(&mut self, attributes: &ParticleAttributes, count: u32, fill_style: FillStyle) {
-    //self.particle_count += count;
-
-    //match fill_style {
-    //FillStyle::WhiteNoise => self.fill_white_noise(attributes, count),
-    //}
-    //}
-
-    //pub fn add_emitter(&mut self, options: EmitterOptions) {
-    //self.emitters.push(Emitter::new(self.position, options));
-    //}
-
-    //pub fn draw(&mut self) {
-    //let update_gui = self.frame % 50 == 0;
-    //if update_gui {
-    //self.duration = self.lifetime.elapsed().as_micros();
-    //}
-
-    //let elapsed_ms = self.lifetime.elapsed().as_millis();
-
-    //for vec_index in 0..self.possibility_spots.len() {
-    //for spot_index in (0..self.possibility_spots[vec_index].len()).rev() {
-    //if self.possibility_spots[vec_index][spot_index].queue_frame == self.frame {
-    //continue;
-    //}
-
-    //self.update_spot(vec_index, spot_index, elapsed_ms);
-    //}
-    //}
-
-    //for i in (0..self.emitters.len()).rev() {
-    //let mut emitter = self.emitters.swap_remove(i);
-    //emitter.emit();
-
-    //if !emitter.delete {
-    //self.emitters.push(emitter);
-    //}
-    //}
-
-    //self.frame += 1;
-
-    //if update_gui {
-    //let end = self.lifetime.elapsed().as_micros();
-    //self.duration = end - self.duration;
-    //self.fps = get_fps();
-
-    //self.emitted_particle_count = self
-    //.emitters
-    //.iter()
-    //.fold(0, |acc, emitter| acc + emitter.particle_count);
-    //}
-    //}
-
-    //pub fn draw_ui(&mut self) {
-    //draw_text(
-    //format!(
-    //"Particle count: {}",
-    //self.particle_count + self.emitted_particle_count
-    //)
-    //.as_str(),
-    //10.0,
-    //20.0,
-    //20.0,
-    //WHITE,
-    //);
-
-    //draw_text(
-    //format!("Loop time (micro): {}", self.duration).as_str(),
-    //10.0,
-    //40.0,
-    //20.0,
-    //WHITE,
-    //);
-
-    //draw_text(
-    //format!("FPS: {}", self.fps).as_str(),
-    //10.0,
-    //60.0,
-    //20.0,
-    //WHITE,
-    //);
-
-    //if is_mouse_button_pressed(MouseButton::Left) {
-    //let (raw_x, raw_y) = mouse_position();
-    //// x, y on screen is an offset of the the grid position.
-    //let x = raw_x - self.position.x;
-    //let y = raw_y - self.position.y;
-    //let x_index = self.possibility_x_index(x);
-    //let y_index = self.possibility_y_index(y);
-    //let vec_index = self.possibility_index(x_index, y_index);
-
-    //println!("------------------------------");
-    //for particle in self.possibility_spots[vec_index].iter() {
-    //let inside_x = particle.x <= x && x <= particle.x + particle.diameter;
-    //let inside_y = particle.y <= y && y <= particle.y + particle.diameter;
-
-    //// No collision
-    //if inside_x && inside_y {
-    //println!("{:#?}", particle);
-    //}
-    //}
-    //}
-
-    //fn draw_grid(grid: &Grid) {
-    //for x_index in 0..grid.possibility_x_count * grid.cell_x_count {
-    //for y_index in 0..grid.possibility_y_count * grid.cell_y_count {
-    //let x = grid.position.x + (x_index * grid.possibility_side_length) as f32;
-    //let y = grid.position.y + (y_index * grid.possibility_side_length) as f32;
-
-    //draw_rectangle_lines(
-    //x,
-    //y,
-    //grid.possibility_side_length as f32,
-    //grid.possibility_side_length as f32,
-    //0.3,
-    //LIGHTGRAY,
-    //);
-    //}
-    //}
-    //}
-
-    ////draw_grid(&self);
-    //}
-
-    //fn possibility_taken(&self, x_coord: f32, y_coord: f32) -> bool {
-    //let cell_x_index = self.cell_x_index(x_coord);
-    //let cell_y_index = self.cell_y_index(y_coord);
-
-    //let poss_x_index = self.possibility_x_index(x_coord);
-    //let poss_y_index = self.possibility_y_index(y_coord);
-
-    //self.possibility_spots[self.possibility_index(poss_x_index, poss_y_index)]
-    //.iter()
-    //.any(|p| {
-    //self.cell_x_index(p.x) == cell_x_index && self.cell_y_index(p.y) == cell_y_index
-    //})
-    //}
-
-    //fn fill_white_noise(&mut self, attributes: &ParticleAttributes, count: u32) {
-    //let mut i: u32 = 0;
-    //let lifetime = Rc::new(Instant::now());
-    //while i < count {
-    //let x_coord = rand::gen_range(0., self.position.width);
-    //let y_coord = rand::gen_range(0., self.position.height);
-    //if !self.possibility_taken(x_coord, y_coord) {
-    //self.add_particle(x_coord, y_coord, attributes, lifetime.clone());
-    //i += 1;
-    //}
-    //}
-    //}
-
-    //fn add_particle(
-    //&mut self,
-    //x_coord: f32,
-    //y_coord: f32,
-    //attributes: &ParticleAttributes,
-    //lifetime: Rc<Instant>,
-    //) {
-    //let particle = Particle::new(x_coord, y_coord, attributes, lifetime);
-    //let poss_x_index = self.possibility_x_index(x_coord);
-    //let poss_y_index = self.possibility_y_index(y_coord);
-    //let poss_index = self.possibility_index(poss_x_index, poss_y_index);
-    //self.possibility_spots[poss_index].push(particle);
-    //}
+    /// Calls f once for every pair of particles within radius of each other.
+    pub fn for_each_pair(&self, radius: f32, mut f: impl FnMut(&GridEntry, &GridEntry)) {
+        let radius_pow = radius.powi(2);
+        let reach = (radius / self.cell_size).ceil().max(1.) as i32;
+
+        // Only neighbors that come after the cell are visited, so every pair is found once.
+        let mut neighbor_offsets = Vec::new();
+        for x in -reach..=reach {
+            for y in -reach..=reach {
+                for z in -reach..=reach {
+                    if (0, 0, 0) < (x, y, z) {
+                        neighbor_offsets.push(IVec3::new(x, y, z));
+                    }
+                }
+            }
+        }
+
+        for (index, cell) in self.cells.iter() {
+            // Cells emptied by clear are kept for their allocation.
+            if cell.is_empty() {
+                continue;
+            }
+
+            for (i, a) in cell.iter().enumerate() {
+                for b in cell[i + 1..].iter() {
+                    if a.position.distance_squared(b.position) <= radius_pow {
+                        f(a, b);
+                    }
+                }
+            }
+
+            for offset in neighbor_offsets.iter() {
+                let neighbor = match self.cells.get(&(*index + *offset)) {
+                    Some(neighbor) => neighbor,
+                    None => continue,
+                };
+
+                for a in cell.iter() {
+                    for b in neighbor.iter() {
+                        if a.position.distance_squared(b.position) <= radius_pow {
+                            f(a, b);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn update_grid_system(
+    mut grid: ResMut<Grid>,
+    moved_query: Query<(Entity, &GlobalTransform), (With<Particle>, Changed<GlobalTransform>)>,
+    particles_query: Query<(), With<Particle>>,
+) {
+    grid.retain(|entity| particles_query.get(entity).is_ok());
+
+    for (entity, global_transform) in moved_query.iter() {
+        grid.insert(entity, global_transform.translation);
+    }
 }
 
 #[cfg(test)]
 mod test {
-    //use crate::force::force;
-    //use crate::grid::test::force::Force;
-    //use crate::particle::*;
-    //use crate::FillStyle;
-    //use crate::ForceBuilder;
-    //use crate::ForceType;
-    //use crate::Grid;
-    //use crate::GridOptions;
-    //use crate::ParticleAttributes;
-    //use crate::Position;
-
-    //fn default_grid() -> Grid {
-    //let options = GridOptions {
-    //cell_x_count: 5,
-    //cell_y_count: 5,
-    //possibility_x_count: 10,
-    //possibility_y_count: 10,
-    //possibility_side_length: 10,
-    //position: Position::new(1., 2.),
-    //force_handler: Vec::new(),
-    //};
-
-    //Grid::new(options)
-    //}
-
-    //fn default_attributes() -> ParticleAttributes {
-    //ParticleAttributes {
-    //color: Color::from_rgba(20, 20, 200, 255),
-    //friction_coefficient: 0.5,
-    //diameter: 5.,
-    //elasticity: 0.9,
-    //mass: 1.,
-    //animation_start_at: InitAnimation::Zero,
-    //}
-    //}
-
-    //fn default_forces() -> Vec<Force> {
-    //let mut builder = ForceBuilder::new();
-
-    //builder.add(
-    //ForceType::Static {
-    //vx: 0.02,
-    //vy: 0.015,
-    //},
-    //50,
-    //);
-
-    //builder.add(ForceType::None, 100);
-
-    //builder.add(
-    //ForceType::Newton {
-    //nx: -0.02,
-    //ny: -0.01,
-    //},
-    //50,
-    //);
-
-    //builder.add(
-    //ForceType::Accelerate {
-    //vx: -0.1,
-    //vy: -0.01,
-    //vx_max: -0.5,
-    //vy_max: -1.5,
-    //},
-    //30,
-    //);
-
-    //builder.build()
-    //}
-
-    //#[test]
-    //fn create_grid() {
-    //let grid = default_grid();
-    //assert_eq!(grid.cell_width, 100); // 10 * 10
-    //assert_eq!(grid.cell_height, 100);
-    //assert_eq!(grid.possibility_spots.len(), 100); // 10 * 10
-    //assert_eq!(grid.width, 500.);
-    //assert_eq!(grid.height, 500.);
-    //assert_eq!(grid.position.x, 1.);
-    //assert_eq!(grid.position.y, 2.);
-    //}
-
-    //#[test]
-    //fn fill_grid() {
-    //let mut grid = default_grid();
-    //let attributes = default_attributes();
-
-    //grid.fill(&attributes, 200, FillStyle::WhiteNoise);
-
-    //assert_eq!(grid.particle_count, 200);
-
-    //let len = grid
-    //.possibility_spots
-    //.iter()
-    //.fold(0, |acc, x| acc + x.len()) as u32;
-
-    //assert_eq!(grid.particle_count, len);
-    //}
-
-    //#[test]
-    //fn updates_frame() {
-    //let mut grid = default_grid();
-    //assert_eq!(0, grid.frame);
-    //grid.draw();
-    //assert_eq!(1, grid.frame);
-    //}
-
-    //#[test]
-    //fn add_particle() {
-    //let mut grid = default_grid();
-    //let attributes = default_attributes();
-
-    //grid.add_particle(115., 105., &attributes);
-    //assert_eq!(1, grid.possibility_spots[1].len());
-
-    //// if y is 1 more, then the pos in array is + poss_x_count (10).
-    //grid.add_particle(105., 115., &attributes);
-    //assert_eq!(1, grid.possibility_spots[10].len());
-
-    //let particle = &grid.possibility_spots[1][0];
-
-    //assert_eq!(1, grid.possibility_x_index(particle.x));
-    //assert_eq!(0, grid.possibility_y_index(particle.y));
-
-    //assert_eq!(1, grid.cell_x_index(particle.x));
-    //assert_eq!(1, grid.cell_y_index(particle.y));
-
-    //// colors is a number between 0 - 1, (255 / 255).
-    //assert_eq!(1., particle.color.a);
-
-    //assert_eq!(5., particle.diameter);
-    //assert_eq!(2.5, particle.radius);
-    //assert_eq!(0.5, particle.friction);
-    //assert_eq!(0.9, particle.elasticity);
-    //assert_eq!(1., particle.mass);
-    //}
-
-    //async fn moves_particle() {
-    //let mut grid = default_grid();
-    //let attributes = default_attributes();
-
-    //grid.add_particle(5., 5., &attributes);
-
-    //assert_eq!(1, grid.possibility_spots[0].len());
-
-    //grid.possibility_spots[0][0].vx += 10.;
-    //grid.draw();
-
-    //assert_eq!(1, grid.possibility_spots[1].len());
-    //assert_eq!(0, grid.possibility_spots[0].len());
-    //}
-
-    //#[test]
-    //fn add_external_forces() {
-    //let forces = default_forces();
-
-    //assert_eq!(forces.len(), 4);
-    //assert_eq!(forces.last().unwrap().until_frame, 230);
-    //}
-
-    //#[test]
-    //fn apply_forces() {
-    //// TODO
-    //let attributes = default_attributes();
-    //let forces = default_forces();
-
-    //let options = GridOptions {
-    //cell_x_count: 5,
-    //cell_y_count: 5,
-    //possibility_x_count: 10,
-    //possibility_y_count: 10,
-    //possibility_side_length: 10,
-    //position: Position::new(1., 2.),
-    //force_handler: forces,
-    //};
-
-    //let mut grid = Grid::new(options);
-    //}
+    use super::*;
+
+    fn entities(count: usize) -> Vec<Entity> {
+        let mut world = World::new();
+        (0..count).map(|_| world.spawn().id()).collect()
+    }
+
+    fn sorted(entries: Vec<GridEntry>) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = entries.iter().map(|entry| entry.entity).collect();
+        entities.sort();
+        entities
+    }
+
+    #[test]
+    fn add_particle() {
+        let mut grid = Grid::new(10.);
+        let entities = entities(2);
+
+        grid.insert(entities[0], Vec3::new(15., 5., -5.));
+        grid.insert(entities[1], Vec3::new(5., 15., 5.));
+
+        assert_eq!(2, grid.particle_count);
+        assert_eq!(
+            IVec3::new(1, 0, -1),
+            grid.cell_index(Vec3::new(15., 5., -5.))
+        );
+        assert_eq!(
+            Vec3::new(15., 5., -5.),
+            grid.get(entities[0]).unwrap().position
+        );
+        assert_eq!(1, grid.cells[&IVec3::new(1, 0, -1)].len());
+        assert_eq!(1, grid.cells[&IVec3::new(0, 1, 0)].len());
+    }
+
+    #[test]
+    fn moves_particle() {
+        let mut grid = Grid::new(10.);
+        let entity = entities(1)[0];
+
+        grid.insert(entity, Vec3::new(5., 5., 5.));
+        grid.insert(entity, Vec3::new(6., 5., 5.));
+
+        assert_eq!(1, grid.cells[&IVec3::ZERO].len());
+        assert_eq!(Vec3::new(6., 5., 5.), grid.get(entity).unwrap().position);
+
+        grid.insert(entity, Vec3::new(15., 5., 5.));
+
+        assert_eq!(1, grid.particle_count);
+        assert!(!grid.cells.contains_key(&IVec3::ZERO));
+        assert_eq!(1, grid.cells[&IVec3::new(1, 0, 0)].len());
+    }
+
+    #[test]
+    fn removes_particles() {
+        let mut grid = Grid::new(10.);
+        let entities = entities(3);
+
+        for (i, entity) in entities.iter().enumerate() {
+            grid.insert(*entity, Vec3::splat(i as f32));
+        }
+
+        assert!(grid.remove(entities[0]));
+        assert!(!grid.remove(entities[0]));
+
+        grid.retain(|entity| entity != entities[1]);
+
+        assert_eq!(1, grid.particle_count);
+        assert!(grid.get(entities[1]).is_none());
+        assert!(grid.get(entities[2]).is_some());
+        assert_eq!(1, grid.locations.len());
+        // Only the cell of the last particle is left.
+        assert_eq!(1, grid.cells.len());
+
+        grid.clear();
+
+        assert_eq!(0, grid.particle_count);
+        assert!(grid.get(entities[2]).is_none());
+    }
+
+    #[test]
+    fn queries_radius() {
+        let mut grid = Grid::new(1.);
+        let entities = entities(3);
+
+        grid.insert(entities[0], Vec3::ZERO);
+        grid.insert(entities[1], Vec3::new(1.5, 0., 0.));
+        grid.insert(entities[2], Vec3::new(0., -2.5, 0.));
+
+        assert_eq!(vec![entities[0]], sorted(grid.query_radius(Vec3::ZERO, 1.)));
+        assert_eq!(
+            entities[0..2].to_vec(),
+            sorted(grid.query_radius(Vec3::ZERO, 2.))
+        );
+        assert_eq!(entities, sorted(grid.query_radius(Vec3::ZERO, 3.)));
+        // More cells than the grid has, so the occupied cells are visited.
+        assert_eq!(entities, sorted(grid.query_radius(Vec3::ZERO, 1e9)));
+    }
+
+    #[test]
+    fn queries_aabb() {
+        let mut grid = Grid::new(1.);
+        let entities = entities(3);
+
+        grid.insert(entities[0], Vec3::ZERO);
+        grid.insert(entities[1], Vec3::new(1.5, 0.5, 0.));
+        grid.insert(entities[2], Vec3::new(1.5, 3., 0.));
+
+        let result = grid.query_aabb(Vec3::new(-0.5, -0.5, -0.5), Vec3::new(2., 1., 0.5));
+
+        assert_eq!(entities[0..2].to_vec(), sorted(result));
+    }
+
+    #[test]
+    fn finds_each_pair_once() {
+        let mut grid = Grid::new(1.);
+        let entities = entities(4);
+
+        grid.insert(entities[0], Vec3::new(0.9, 0.9, 0.9));
+        grid.insert(entities[1], Vec3::new(1.1, 1.1, 1.1));
+        grid.insert(entities[2], Vec3::new(1.2, 1.1, 1.1));
+        grid.insert(entities[3], Vec3::new(5., 5., 5.));
+
+        let mut pairs = Vec::new();
+        grid.for_each_pair(0.5, |a, b| {
+            let mut pair = [a.entity, b.entity];
+            pair.sort();
+            pairs.push(pair);
+        });
+        pairs.sort();
+
+        assert_eq!(
+            vec![
+                [entities[0], entities[1]],
+                [entities[0], entities[2]],
+                [entities[1], entities[2]],
+            ],
+            pairs
+        );
+    }
 }
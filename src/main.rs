@@ -1,7 +1,17 @@
 #![allow(dead_code)]
 
 use crate::angles::Angles;
+use crate::collisions::collider::ColliderPlugin;
+use crate::collisions::particle_collision::ParticleCollisionPlugin;
+use crate::collisions::sleep::SleepPlugin;
+use crate::emitters::boids::BoidsPlugin;
 use crate::emitters::emitter::EmitterSize;
+use crate::fluids::dam_break::spawn_dam_break;
+use crate::fluids::sph::FluidPlugin;
+use crate::forces::force_field::{ForceFieldPlugin, ALL_LAYERS};
+use crate::grid::GridPlugin;
+use crate::math::distribution::Distribution;
+use crate::math::vector_grid::VectorGridPlugin;
 use crate::pattern::emitter_animations;
 use crate::pattern::random_forces;
 use crate::pattern::shimmer_animations;
@@ -15,7 +25,9 @@ use emitters::emitter::{Emitter, EmitterOptions, EmitterPlugin};
 
 mod animations;
 mod collision;
+mod collisions;
 mod emitters;
+mod fluids;
 //mod fill_style;
 mod angles;
 mod dev;
@@ -34,6 +46,14 @@ fn main() {
         .add_plugin(DevUIPlugin)
         .add_plugin(DevCameraPlugin)
         .add_plugin(EmitterPlugin)
+        .add_plugin(ForceFieldPlugin)
+        .add_plugin(GridPlugin)
+        .add_plugin(ParticleCollisionPlugin)
+        .add_plugin(ColliderPlugin)
+        .add_plugin(SleepPlugin)
+        .add_plugin(BoidsPlugin)
+        .add_plugin(FluidPlugin)
+        .add_plugin(VectorGridPlugin)
         .run();
 }
 
@@ -74,6 +94,7 @@ fn setup(
         emission_distortion: 0.,
         //emitter_velocity: Velocity::new(10., -15., 10.),
         emitter_velocity: Velocity::zero(),
+        inherit_velocity: Vec3::splat(1.),
         particle_color: Color::Rgba {
             red: 0.5,
             green: 1.0,
@@ -82,25 +103,48 @@ fn setup(
         },
         particles_per_emission: 20,
         delay_between_emission_ms: 100,
-        particle_lifetime: Duration::from_secs(5),
-        particle_radius: 0.1,
-        particle_mass: 1.,
-        particle_speed: 30.,
-        particle_friction_coefficient: 0.005,
+        particles_per_distance: 0.,
+        particle_lifetime: Distribution::Uniform {
+            min: Duration::from_secs(4),
+            max: Duration::from_secs(5),
+        },
+        particle_radius: Distribution::Constant(0.1),
+        particle_mass: Distribution::Normal {
+            mean: 1.,
+            std_dev: 0.1,
+        },
+        particle_speed: Distribution::Constant(30.),
+        particle_friction_coefficient: Distribution::Constant(0.005),
+        particle_rotation: None,
+        particle_temperature: None,
+        particle_collisions: None,
+        collider_response: None,
+        substeps: None,
+        sleep: None,
+        boids: None,
+        fluid: None,
         force_handler: random_forces(),
+        force_field_layers: ALL_LAYERS,
         bounds: None,
-        //bounds: Some(Bounds {
-        //start_x: None,
-        //start_y: Some(0.),
-        //start_z: None,
-        //end_x: None,
-        //end_y: None,
-        //end_z: None,
-        //}),
+        target_shape: None,
+        //bounds: Some(Bounds::new(
+        //BoundsShape::Box {
+        //min: Vec3::new(f32::NEG_INFINITY, 0., f32::NEG_INFINITY),
+        //max: Vec3::splat(f32::INFINITY),
+        //},
+        //BoundsSpace::World,
+        //BoundsMode::Bounce { restitution: 0.6 },
+        //)),
         emitter_animation_handler: emitter_animations(),
         particle_animation_options: Some(shimmer_animations()),
     };
 
     let total_elapsed_ms = time.time_since_startup().as_millis();
-    Emitter::create(options, &mut commands, meshes, materials, total_elapsed_ms);
+
+    // cargo run --features dam_break
+    if cfg!(feature = "dam_break") {
+        spawn_dam_break(&mut commands, meshes, materials, total_elapsed_ms);
+    } else {
+        Emitter::create(options, &mut commands, meshes, materials, total_elapsed_ms);
+    }
 }
@@ -0,0 +1,370 @@
+use rand::prelude::ThreadRng;
+use rand::Rng;
+use std::f32::consts::PI;
+use std::fmt;
+use std::time::Duration;
+
+/// Values which can be drawn from a distribution, sampling happens in f32.
+pub trait DistributionValue: Copy {
+    fn to_f32(self) -> f32;
+    fn from_f32(val: f32) -> Self;
+}
+
+impl DistributionValue for f32 {
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_f32(val: f32) -> Self {
+        val
+    }
+}
+
+impl DistributionValue for u128 {
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    fn from_f32(val: f32) -> Self {
+        val.round() as u128
+    }
+}
+
+impl DistributionValue for Duration {
+    fn to_f32(self) -> f32 {
+        self.as_secs_f32()
+    }
+
+    fn from_f32(val: f32) -> Self {
+        Duration::from_secs_f32(val.max(0.))
+    }
+}
+
+/// Every particle that is spawned draws a new value from the distribution.
+#[derive(Debug, Clone)]
+pub enum Distribution<T> {
+    Constant(T),
+    Uniform {
+        min: T,
+        max: T,
+    },
+    Normal {
+        mean: T,
+        std_dev: T,
+    },
+    /// Values with their (relative) weight, use weighted_choice to check the weights.
+    WeightedChoice(Vec<(T, f32)>),
+    /// Points of (emitter elapsed ms, value), interpolated linearly.
+    /// Use curve to sort the points.
+    Curve(Vec<(u128, T)>),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DistributionError {
+    /// Curves and weighted choices need at least one value.
+    Empty,
+    /// Weights have to be finite and not negative, with at least one above zero.
+    InvalidWeights,
+}
+
+impl<T: DistributionValue> Distribution<T> {
+    pub fn weighted_choice(choices: Vec<(T, f32)>) -> Result<Self, DistributionError> {
+        if choices.is_empty() {
+            return Err(DistributionError::Empty);
+        }
+
+        let valid = choices
+            .iter()
+            .all(|(_, weight)| weight.is_finite() && 0. <= *weight);
+
+        if !valid || !choices.iter().any(|(_, weight)| 0. < *weight) {
+            return Err(DistributionError::InvalidWeights);
+        }
+
+        Ok(Distribution::WeightedChoice(choices))
+    }
+
+    /// Curve through the points, sorted by time.
+    pub fn curve(mut points: Vec<(u128, T)>) -> Result<Self, DistributionError> {
+        if points.is_empty() {
+            return Err(DistributionError::Empty);
+        }
+
+        points.sort_by_key(|(ms, _)| *ms);
+        Ok(Distribution::Curve(points))
+    }
+
+    pub fn sample(&self, rng: &mut ThreadRng, emitter_elapsed_ms: u128) -> T {
+        match self {
+            Distribution::Constant(val) => *val,
+            Distribution::Uniform { min, max } => {
+                let (min, max) = (min.to_f32(), max.to_f32());
+
+                if min < max {
+                    T::from_f32(rng.gen_range(min..max))
+                } else {
+                    T::from_f32(min)
+                }
+            }
+            Distribution::Normal { mean, std_dev } => {
+                // Box-Muller transform.
+                let u1: f32 = 1. - rng.gen_range(0_f32..1.);
+                let u2: f32 = rng.gen_range(0_f32..1.);
+                let z = (-2. * u1.ln()).sqrt() * (2. * PI * u2).cos();
+
+                T::from_f32(mean.to_f32() + z * std_dev.to_f32())
+            }
+            Distribution::WeightedChoice(choices) => {
+                // Choices that weren't checked by weighted_choice fall back to the first or zero.
+                let fallback = choices
+                    .first()
+                    .map_or_else(|| T::from_f32(0.), |(val, _)| *val);
+                let total_weight: f32 = choices.iter().map(|(_, weight)| weight.max(0.)).sum();
+
+                if !(0. < total_weight && total_weight.is_finite()) {
+                    return fallback;
+                }
+
+                let mut remainder = rng.gen_range(0_f32..total_weight);
+
+                for (val, weight) in choices.iter() {
+                    if remainder < *weight {
+                        return *val;
+                    }
+
+                    remainder -= weight.max(0.);
+                }
+
+                choices.last().map_or(fallback, |(val, _)| *val)
+            }
+            Distribution::Curve(points) => T::from_f32(
+                sample_curve(points, emitter_elapsed_ms as f32, |(ms, val)| {
+                    (*ms as f32, val.to_f32())
+                })
+                .unwrap_or(0.),
+            ),
+        }
+    }
+
+    /// Interpolates the parameters of two distributions, used to animate them.
+    /// Distributions of a different kind switch halfway.
+    pub fn lerp(&self, other: &Distribution<T>, fraction: f32) -> Distribution<T> {
+        let lerp = |from: &T, to: &T| {
+            T::from_f32(from.to_f32() + fraction * (to.to_f32() - from.to_f32()))
+        };
+
+        match (self, other) {
+            (Distribution::Constant(from), Distribution::Constant(to)) => {
+                Distribution::Constant(lerp(from, to))
+            }
+            (Distribution::Constant(from), Distribution::Uniform { min, max }) => {
+                Distribution::Uniform {
+                    min: lerp(from, min),
+                    max: lerp(from, max),
+                }
+            }
+            (Distribution::Uniform { min, max }, Distribution::Constant(to)) => {
+                Distribution::Uniform {
+                    min: lerp(min, to),
+                    max: lerp(max, to),
+                }
+            }
+            (
+                Distribution::Uniform { min, max },
+                Distribution::Uniform {
+                    min: to_min,
+                    max: to_max,
+                },
+            ) => Distribution::Uniform {
+                min: lerp(min, to_min),
+                max: lerp(max, to_max),
+            },
+            (
+                Distribution::Normal { mean, std_dev },
+                Distribution::Normal {
+                    mean: to_mean,
+                    std_dev: to_std_dev,
+                },
+            ) => Distribution::Normal {
+                mean: lerp(mean, to_mean),
+                std_dev: lerp(std_dev, to_std_dev),
+            },
+            _ if fraction < 0.5 => self.clone(),
+            _ => other.clone(),
+        }
+    }
+
+    pub fn map<U>(&self, f: impl Fn(T) -> U) -> Distribution<U> {
+        match self {
+            Distribution::Constant(val) => Distribution::Constant(f(*val)),
+            Distribution::Uniform { min, max } => Distribution::Uniform {
+                min: f(*min),
+                max: f(*max),
+            },
+            Distribution::Normal { mean, std_dev } => Distribution::Normal {
+                mean: f(*mean),
+                std_dev: f(*std_dev),
+            },
+            Distribution::WeightedChoice(choices) => Distribution::WeightedChoice(
+                choices
+                    .iter()
+                    .map(|(val, weight)| (f(*val), *weight))
+                    .collect(),
+            ),
+            Distribution::Curve(points) => {
+                Distribution::Curve(points.iter().map(|(ms, val)| (*ms, f(*val))).collect())
+            }
+        }
+    }
+}
+
+/// Linear interpolation between points of (x, y), clamped to the first and last point.
+/// Points should be sorted by x, unsorted points give a wrong value but don't panic.
+/// None without points.
+pub fn sample_curve<P>(points: &[P], x: f32, point: impl Fn(&P) -> (f32, f32)) -> Option<f32> {
+    let (first_x, first_y) = point(points.first()?);
+
+    if x <= first_x {
+        return Some(first_y);
+    }
+
+    for window in points.windows(2) {
+        let (from_x, from) = point(&window[0]);
+        let (until_x, to) = point(&window[1]);
+
+        if x < until_x {
+            let fraction = if from_x < until_x {
+                ((x - from_x) / (until_x - from_x)).min(1.)
+            } else {
+                1.
+            };
+
+            return Some(from + fraction * (to - from));
+        }
+    }
+
+    points.last().map(|last| point(last).1)
+}
+
+impl fmt::Display for DistributionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DistributionError::Empty => write!(f, "distribution needs at least one value"),
+            DistributionError::InvalidWeights => write!(
+                f,
+                "weights have to be finite and not negative, with at least one above zero"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DistributionError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::thread_rng;
+
+    fn samples<T: DistributionValue>(distribution: &Distribution<T>, elapsed_ms: u128) -> Vec<T> {
+        let mut rng = thread_rng();
+        (0..1000)
+            .map(|_| distribution.sample(&mut rng, elapsed_ms))
+            .collect()
+    }
+
+    #[test]
+    fn samples_uniform_and_normal() {
+        let uniform = Distribution::Uniform {
+            min: 1_f32,
+            max: 2.,
+        };
+        assert!(samples(&uniform, 0)
+            .iter()
+            .all(|val| (1. ..2.).contains(val)));
+
+        // Swapped bounds are the min.
+        let swapped = Distribution::Uniform {
+            min: 2_f32,
+            max: 1.,
+        };
+        assert!(samples(&swapped, 0).iter().all(|val| *val == 2.));
+
+        let normal = Distribution::Normal {
+            mean: 10_f32,
+            std_dev: 1.,
+        };
+        let values = samples(&normal, 0);
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        assert!((mean - 10.).abs() < 0.2);
+    }
+
+    #[test]
+    fn weighted_choice_skips_zero_weights() {
+        let choice = Distribution::weighted_choice(vec![(1_f32, 0.), (2., 1.), (3., 3.)]).unwrap();
+        let values = samples(&choice, 0);
+
+        assert!(!values.contains(&1.));
+        let threes = values.iter().filter(|val| **val == 3.).count();
+        assert!(650 < threes && threes < 850);
+    }
+
+    #[test]
+    fn weighted_choice_checks_weights() {
+        assert_eq!(
+            Distribution::<f32>::weighted_choice(vec![]).unwrap_err(),
+            DistributionError::Empty
+        );
+
+        for weights in [
+            vec![0., 0.],
+            vec![1., -1.],
+            vec![f32::NAN],
+            vec![f32::INFINITY],
+        ] {
+            let choices = weights.into_iter().map(|weight| (1_f32, weight)).collect();
+            assert_eq!(
+                Distribution::weighted_choice(choices).unwrap_err(),
+                DistributionError::InvalidWeights
+            );
+        }
+
+        // Unchecked choices fall back instead of panicking.
+        assert_eq!(
+            samples(&Distribution::<f32>::WeightedChoice(vec![]), 0)[0],
+            0.
+        );
+        let zero_weights = Distribution::WeightedChoice(vec![(4_f32, 0.), (5., 0.)]);
+        assert_eq!(samples(&zero_weights, 0)[0], 4.);
+    }
+
+    #[test]
+    fn curve_interpolates_sorted_points() {
+        let curve = Distribution::curve(vec![(2000, 10_f32), (0, 0.), (1000, 20.)]).unwrap();
+        let mut rng = thread_rng();
+
+        assert_eq!(curve.sample(&mut rng, 0), 0.);
+        assert_eq!(curve.sample(&mut rng, 500), 10.);
+        assert_eq!(curve.sample(&mut rng, 1500), 15.);
+        assert_eq!(curve.sample(&mut rng, 5000), 10.);
+
+        let delayed = Distribution::curve(vec![(1000, 5_u128)]).unwrap();
+        assert_eq!(delayed.sample(&mut rng, 0), 5);
+    }
+
+    #[test]
+    fn curve_edge_cases() {
+        assert_eq!(
+            Distribution::<f32>::curve(vec![]).unwrap_err(),
+            DistributionError::Empty
+        );
+
+        let mut rng = thread_rng();
+        assert_eq!(Distribution::<f32>::Curve(vec![]).sample(&mut rng, 10), 0.);
+
+        // Unchecked, unsorted and duplicate points don't panic.
+        let unsorted = Distribution::Curve(vec![(1000, 1_f32), (0, 2.), (0, 3.)]);
+        for elapsed_ms in [0, 500, 1000, 2000] {
+            assert!(unsorted.sample(&mut rng, elapsed_ms).is_finite());
+        }
+    }
+}
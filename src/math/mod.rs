@@ -1 +1,4 @@
+pub mod distribution;
+pub mod noise;
+pub mod vector_grid;
 pub mod velocity;
@@ -0,0 +1,141 @@
+use bevy::math::Vec3;
+
+/// Ken Perlin's reference permutation.
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+// Offsets used to get three uncorrelated noise values out of one noise function.
+const OFFSET_Y: [f32; 3] = [31.416, -47.853, 12.793];
+const OFFSET_Z: [f32; 3] = [-233.14, 95.372, -61.519];
+
+const CURL_EPSILON: f32 = 0.01;
+
+fn hash(i: usize) -> usize {
+    PERMUTATION[i & 255] as usize
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn grad(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+
+    let u = if h & 1 == 0 { u } else { -u };
+    let v = if h & 2 == 0 { v } else { -v };
+    u + v
+}
+
+/// Improved Perlin noise, returns a value between -1 and 1.
+pub fn perlin(point: Vec3) -> f32 {
+    let xi = (point.x.floor() as i32 & 255) as usize;
+    let yi = (point.y.floor() as i32 & 255) as usize;
+    let zi = (point.z.floor() as i32 & 255) as usize;
+
+    let x = point.x - point.x.floor();
+    let y = point.y - point.y.floor();
+    let z = point.z - point.z.floor();
+
+    let u = fade(x);
+    let v = fade(y);
+    let w = fade(z);
+
+    let a = hash(xi) + yi;
+    let aa = hash(a) + zi;
+    let ab = hash(a + 1) + zi;
+    let b = hash(xi + 1) + yi;
+    let ba = hash(b) + zi;
+    let bb = hash(b + 1) + zi;
+
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, grad(hash(aa), x, y, z), grad(hash(ba), x - 1., y, z)),
+            lerp(
+                u,
+                grad(hash(ab), x, y - 1., z),
+                grad(hash(bb), x - 1., y - 1., z),
+            ),
+        ),
+        lerp(
+            v,
+            lerp(
+                u,
+                grad(hash(aa + 1), x, y, z - 1.),
+                grad(hash(ba + 1), x - 1., y, z - 1.),
+            ),
+            lerp(
+                u,
+                grad(hash(ab + 1), x, y - 1., z - 1.),
+                grad(hash(bb + 1), x - 1., y - 1., z - 1.),
+            ),
+        ),
+    )
+}
+
+/// Fractal (fbm) Perlin noise, every octave doubles the frequency and halves the amplitude.
+/// Returns a value between -1 and 1.
+pub fn fractal(point: Vec3, octaves: u32) -> f32 {
+    let mut total = 0.;
+    let mut frequency = 1.;
+    let mut amplitude = 1.;
+    let mut max_amplitude = 0.;
+
+    for _ in 0..octaves.max(1) {
+        total += perlin(point * frequency) * amplitude;
+        max_amplitude += amplitude;
+        frequency *= 2.;
+        amplitude *= 0.5;
+    }
+
+    total / max_amplitude
+}
+
+/// Three uncorrelated fractal noise values at the same point.
+pub fn fractal_vec3(point: Vec3, octaves: u32) -> Vec3 {
+    Vec3::new(
+        fractal(point, octaves),
+        fractal(point + Vec3::from(OFFSET_Y), octaves),
+        fractal(point + Vec3::from(OFFSET_Z), octaves),
+    )
+}
+
+/// Curl of a fractal noise potential field. The field is divergence free, so particles that
+/// follow it swirl around each other instead of bunching up.
+pub fn curl(point: Vec3, octaves: u32) -> Vec3 {
+    let dx = Vec3::new(CURL_EPSILON, 0., 0.);
+    let dy = Vec3::new(0., CURL_EPSILON, 0.);
+    let dz = Vec3::new(0., 0., CURL_EPSILON);
+
+    let d_dx = fractal_vec3(point + dx, octaves) - fractal_vec3(point - dx, octaves);
+    let d_dy = fractal_vec3(point + dy, octaves) - fractal_vec3(point - dy, octaves);
+    let d_dz = fractal_vec3(point + dz, octaves) - fractal_vec3(point - dz, octaves);
+
+    Vec3::new(d_dy.z - d_dz.y, d_dz.x - d_dx.z, d_dx.y - d_dy.x) / (2. * CURL_EPSILON)
+}
@@ -0,0 +1,313 @@
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+use bevy::math::{IVec3, UVec3, Vec3};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use std::fmt;
+use std::path::Path;
+
+/// Larger grids are rejected, so a broken header can't allocate gigabytes.
+pub const MAX_VOXELS: u32 = 256 * 256 * 256;
+
+/// Vectors baked on a regular 3D grid, e.g. exported from a DCC tool.
+/// Also an asset, `asset_server.load("wind.fga")` loads it with the VectorGridPlugin.
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "5b0a3a8e-2f4c-4d1e-9b67-3c1f2e8d7a40"]
+pub struct VectorGrid {
+    pub resolution: UVec3,
+    pub min: Vec3,
+    pub max: Vec3,
+    /// Voxels ordered by x, then y, then z.
+    vectors: Vec<Vec3>,
+}
+
+/// How a grid is sampled outside of its bounds.
+#[derive(Debug, Clone, Copy)]
+pub enum OutsideBounds {
+    /// Repeats the grid in every direction.
+    Tile,
+    /// Uses the voxels on the edge of the grid.
+    Clamp,
+}
+
+#[derive(Debug)]
+pub enum FgaError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl VectorGrid {
+    pub fn new(resolution: UVec3, min: Vec3, max: Vec3, vectors: Vec<Vec3>) -> Self {
+        assert_eq!(
+            voxel_count(resolution),
+            Some(vectors.len()),
+            "Grid needs one vector per voxel"
+        );
+
+        Self {
+            resolution,
+            min,
+            max,
+            vectors,
+        }
+    }
+
+    pub fn load_fga(path: impl AsRef<Path>) -> Result<Self, FgaError> {
+        let contents = std::fs::read_to_string(path).map_err(FgaError::Io)?;
+        Self::parse_fga(&contents)
+    }
+
+    /// Parses the comma separated FGA format: the resolution, the minimum and maximum bounds
+    /// and then a vector per voxel.
+    pub fn parse_fga(contents: &str) -> Result<Self, FgaError> {
+        let mut values = FgaValues::new(contents);
+
+        let mut resolution = [0; 3];
+        for size in resolution.iter_mut() {
+            let value = values.next_f32("resolution")?;
+
+            if value < 1. || value.fract() != 0. {
+                return Err(values.error(format!(
+                    "resolution must be a positive integer, found {}",
+                    value
+                )));
+            }
+
+            if (MAX_VOXELS as f32) < value {
+                return Err(values.error(format!(
+                    "resolution must be at most {}, found {}",
+                    MAX_VOXELS, value
+                )));
+            }
+
+            *size = value as u32;
+        }
+
+        let resolution = UVec3::from(resolution);
+        let voxel_count = match voxel_count(resolution) {
+            Some(count) if count <= MAX_VOXELS as usize => count,
+            _ => {
+                return Err(values.error(format!(
+                    "resolution {} has more than {} voxels",
+                    resolution, MAX_VOXELS
+                )))
+            }
+        };
+
+        let min = values.next_vec3("minimum bounds")?;
+        let max = values.next_vec3("maximum bounds")?;
+
+        if !min.cmplt(max).all() {
+            return Err(
+                values.error("maximum bounds must be larger than the minimum bounds".to_string())
+            );
+        }
+
+        // Grows with the parsed vectors, a file that ends early doesn't allocate the full grid.
+        let mut vectors = Vec::new();
+
+        for _ in 0..voxel_count {
+            vectors.push(values.next_vec3("voxel vector")?);
+        }
+
+        if let Some((line, value)) = values.values.next() {
+            return Err(FgaError::Parse {
+                line,
+                message: format!("unexpected value '{}' after the last voxel", value),
+            });
+        }
+
+        Ok(Self::new(resolution, min, max, vectors))
+    }
+
+    /// Trilinear interpolation of the voxels, which are centered in their cells.
+    pub fn sample(&self, position: Vec3, outside: OutsideBounds) -> Vec3 {
+        let size = self.resolution.as_vec3();
+        let voxel = (position - self.min) / (self.max - self.min) * size - Vec3::splat(0.5);
+        let base = voxel.floor();
+        let fraction = voxel - base;
+        let base = base.as_ivec3();
+
+        let mut result = Vec3::ZERO;
+        for corner in 0..8 {
+            let offset = IVec3::new(corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let weight = Vec3::select(offset.cmpeq(IVec3::ONE), fraction, Vec3::ONE - fraction);
+
+            result += self.voxel(base + offset, outside) * weight.x * weight.y * weight.z;
+        }
+
+        result
+    }
+
+    fn voxel(&self, index: IVec3, outside: OutsideBounds) -> Vec3 {
+        let resolution = self.resolution.as_ivec3();
+        let index = match outside {
+            OutsideBounds::Tile => IVec3::new(
+                index.x.rem_euclid(resolution.x),
+                index.y.rem_euclid(resolution.y),
+                index.z.rem_euclid(resolution.z),
+            ),
+            OutsideBounds::Clamp => index.clamp(IVec3::ZERO, resolution - IVec3::ONE),
+        };
+
+        let index = index.x + resolution.x * (index.y + resolution.y * index.z);
+        self.vectors[index as usize]
+    }
+}
+
+/// None when the count overflows.
+fn voxel_count(resolution: UVec3) -> Option<usize> {
+    resolution
+        .x
+        .checked_mul(resolution.y)?
+        .checked_mul(resolution.z)
+        .map(|count| count as usize)
+}
+
+/// Values of an FGA file with the line they are on.
+struct FgaValues<'a> {
+    values: Box<dyn Iterator<Item = (usize, &'a str)> + 'a>,
+    line: usize,
+}
+
+impl<'a> FgaValues<'a> {
+    fn new(contents: &'a str) -> Self {
+        let values = contents.lines().enumerate().flat_map(|(index, line)| {
+            line.split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(move |value| (index + 1, value))
+        });
+
+        Self {
+            values: Box::new(values),
+            line: 1,
+        }
+    }
+
+    fn next_f32(&mut self, what: &str) -> Result<f32, FgaError> {
+        let (line, value) = match self.values.next() {
+            Some(next) => next,
+            None => {
+                return Err(self.error(format!("unexpected end of file, expected the {}", what)))
+            }
+        };
+
+        self.line = line;
+        value.parse().map_err(|_| {
+            self.error(format!(
+                "expected a number for the {}, found '{}'",
+                what, value
+            ))
+        })
+    }
+
+    fn next_vec3(&mut self, what: &str) -> Result<Vec3, FgaError> {
+        Ok(Vec3::new(
+            self.next_f32(what)?,
+            self.next_f32(what)?,
+            self.next_f32(what)?,
+        ))
+    }
+
+    /// Error on the line of the last read value.
+    fn error(&self, message: String) -> FgaError {
+        FgaError::Parse {
+            line: self.line,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for FgaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FgaError::Io(error) => write!(f, "could not read FGA file: {}", error),
+            FgaError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for FgaError {}
+
+pub struct VectorGridPlugin;
+
+impl Plugin for VectorGridPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<VectorGrid>()
+            .init_asset_loader::<FgaLoader>();
+    }
+}
+
+#[derive(Default)]
+pub struct FgaLoader;
+
+impl AssetLoader for FgaLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let contents = std::str::from_utf8(bytes)?;
+            let grid = VectorGrid::parse_fga(contents)?;
+            load_context.set_default_asset(LoadedAsset::new(grid));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["fga"]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_error_line(contents: &str) -> usize {
+        match VectorGrid::parse_fga(contents) {
+            Err(FgaError::Parse { line, .. }) => line,
+            result => panic!("expected a parse error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn parses_and_samples() {
+        let grid = VectorGrid::parse_fga(
+            "2,1,1,\n\
+             0,0,0,\n\
+             2,1,1,\n\
+             1,0,0,\n\
+             3,0,0,\n",
+        )
+        .unwrap();
+
+        assert_eq!(grid.resolution, UVec3::new(2, 1, 1));
+        let center = grid.sample(Vec3::new(1., 0.5, 0.5), OutsideBounds::Clamp);
+        assert!((center - Vec3::new(2., 0., 0.)).length() < 0.0001);
+        let edge = grid.sample(Vec3::new(0., 0.5, 0.5), OutsideBounds::Clamp);
+        assert!((edge - Vec3::new(1., 0., 0.)).length() < 0.0001);
+    }
+
+    #[test]
+    fn rejects_bad_resolutions() {
+        assert_eq!(parse_error_line("2,0,1,\n0,0,0,\n1,1,1,\n"), 1);
+        assert_eq!(parse_error_line("2,\n1.5,1,\n0,0,0,\n1,1,1,\n"), 2);
+        assert_eq!(parse_error_line("-1,1,1,\n0,0,0,\n1,1,1,\n"), 1);
+        assert_eq!(parse_error_line("1e10,1,1,\n0,0,0,\n1,1,1,\n"), 1);
+        // Each axis fits, the voxel count doesn't.
+        assert_eq!(parse_error_line("65536,65536,65536,\n0,0,0,\n1,1,1,\n"), 1);
+    }
+
+    #[test]
+    fn reports_the_line_of_bad_values() {
+        assert_eq!(parse_error_line("1,1,1,\n0,0,0,\n1,1,1,\nx,0,0,\n"), 4);
+        assert_eq!(parse_error_line("1,1,1,\n1,0,0,\n0,1,1,\n0,0,0,\n"), 3);
+        // Ends early, on the line of the last value.
+        assert_eq!(parse_error_line("2,1,1,\n0,0,0,\n1,1,1,\n0,0,0,\n"), 4);
+        assert_eq!(
+            parse_error_line("1,1,1,\n0,0,0,\n1,1,1,\n0,0,0,\n\n7,\n"),
+            6
+        );
+    }
+}
@@ -173,7 +173,7 @@ impl Particle {
             + ((other.mass - self.mass) / total_weight * other.vy);
 
         self.vy = transform_vy * self.elasticity;
-        other.vx = other_vy * other.elasticity;
+        other.vy = other_vy * other.elasticity;
 
         self.move_if_overlaps(other);
         true
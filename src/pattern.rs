@@ -19,6 +19,7 @@ use crate::forces::accelerating_force::AcceleratingForce;
 use crate::forces::constant_force::ConstantForce;
 use crate::forces::force_handler::ForceHandler;
 use crate::forces::gravitational_force::GravitationalForce;
+use crate::math::distribution::Distribution;
 use crate::math::velocity;
 use crate::trails::trail_animation::TrailAnimation;
 use crate::trails::trail_animation::TrailOptions;
@@ -245,8 +246,8 @@ pub fn emitter_animations() -> Option<EmitterAnimationHandler> {
     let speed_1 = Box::new(EmitSpeedAnimation {
         from_ms: 0,
         until_ms: 2000,
-        from_speed: 30.,
-        to_speed: 40.,
+        from_speed: Distribution::Uniform { min: 25., max: 35. },
+        to_speed: Distribution::Uniform { min: 35., max: 45. },
     });
 
     //let speed_2 = Box::new(EmitSpeedAnimation {